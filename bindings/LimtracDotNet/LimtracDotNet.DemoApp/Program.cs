@@ -32,8 +32,20 @@ var executionResult = Limtrac.Prepare()
     {
         scmp_enabled     = true,
         scmp_deny_common = true,
+        scmp_deny_tier   = Constants.ScmpDenyTierHardened,
+        scmp_path_policy = false,
+        scmp_deny_exec   = true,
         unshare_common   = true,
-        unshare_network  = true
+        unshare_network  = true,
+        unshare_namespaces = Constants.UnshareNsTime,
+        net_loopback_up  = true,
+        mount_tmpfs      = true,
+        mount_tmpfs_size = 64 * 1000000, // 64 MB
+        mount_dev        = true,
+        
+        priv_no_new_privs = true,
+        priv_drop_caps    = true,
+        priv_not_dumpable = true
     }).Execute();
 
 //var executionResult = new ProcExecResult() { res_usage = new ProcResUsage() };
@@ -42,6 +54,11 @@ Console.WriteLine($"Exit code:\t{executionResult.exit_code}");
 Console.WriteLine($"Exit sign:\t{executionResult.exit_sign}");
 Console.WriteLine($"Is killed:\t{executionResult.is_killed}");
 Console.WriteLine($"Kill reason:\t{executionResult.kill_reason}");
+Console.WriteLine($"Denied calls:\t{executionResult.scmp_denied}");
+Console.WriteLine($"Setup failure:\t{executionResult.setup_failure} (errno {executionResult.setup_errno})");
+Console.WriteLine($"Landlock:\tABI {executionResult.landlock_abi} (access 0x{executionResult.landlock_access:x})");
+Console.WriteLine($"Namespaces:\t0x{executionResult.unshare_created:x}");
+Console.WriteLine($"Group leftover:\t{executionResult.group_leftover}");
 Console.WriteLine();
 Console.WriteLine($"Resources usage -> Processor time:\t{executionResult.res_usage.proc_time}");
 Console.WriteLine($"Resources usage -> Process RSS (b):\t{executionResult.res_usage.proc_wset}");
@@ -14,4 +14,38 @@ public static class Constants
     public const int KillReasonRealTime = 2;
     public const int KillReasonProcTime = 3;
     public const int KillReasonProcWSet = 4;
+
+    public const int SetupFailureNone  = 0;
+    public const int SetupFailureMount = 1;
+    public const int SetupFailureLandlock = 2;
+    public const int SetupFailureCredentials = 3;
+    public const int SetupFailurePrivileges = 4;
+    public const int SetupFailureNamespaces = 5;
+    public const int SetupFailureNetwork = 6;
+    public const int SetupFailureIoStreams = 7;
+    public const int SetupFailureUidPool = 8;
+
+    public const int UnshareNsMount  = 1;
+    public const int UnshareNsIpc    = 1 << 1;
+    public const int UnshareNsUts    = 1 << 2;
+    public const int UnshareNsPid    = 1 << 3;
+    public const int UnshareNsNet    = 1 << 4;
+    public const int UnshareNsCgroup = 1 << 5;
+    public const int UnshareNsUser   = 1 << 6;
+    public const int UnshareNsTime   = 1 << 7;
+
+    public const int ScmpDenyTierBaseline = 0;
+    public const int ScmpDenyTierHardened = 1;
+    public const int ScmpDenyTierParanoid = 2;
+
+    public const int IoModeAuto    = 0;
+    public const int IoModeFile    = 1;
+    public const int IoModeNull    = 2;
+    public const int IoModeInherit = 3;
+    public const int IoModePipe    = 4;
+    public const int IoModeAppend  = 5;
+
+    public const int IoOpenTruncate  = 0;
+    public const int IoOpenAppend    = 1;
+    public const int IoOpenExclusive = 2;
 }
\ No newline at end of file
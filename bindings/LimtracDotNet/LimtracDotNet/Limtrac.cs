@@ -13,6 +13,7 @@ public class Limtrac
     private ExecProgIO     _execProgIo;
     private ExecProgLimits _execProgLimits;
     private ExecProgGuard  _execProgGuard;
+    private LimtracPolicy  _policy;
     
     private Limtrac() {  }
     public static Limtrac Prepare() { return new Limtrac(); }
@@ -41,6 +42,12 @@ public class Limtrac
         return this;
     }
 
+    public Limtrac WithPolicy(LimtracPolicy policy)
+    {
+        _policy = policy;
+        return this;
+    }
+
     private void ThrowIfNotReadyToExecute()
     {
         ArgumentNullException.ThrowIfNull(_execProgInfo,   nameof(ExecProgInfo));
@@ -53,8 +60,30 @@ public class Limtrac
     {
         if (!RuntimeInformation.IsOSPlatform(OSPlatform.Linux))
             throw new PlatformNotSupportedException("Limtrac is available only on Linux!");
+        
+        // Limits and guard are parts of a compiled policy
+        if (_policy != null)
+            return LimtracInterop.PolicyExecute(_policy, _execProgInfo, _execProgIo);
+        
         ThrowIfNotReadyToExecute();
         
         return LimtracInterop.Execute(_execProgInfo!, _execProgIo!, _execProgLimits!, _execProgGuard!);
     }
+
+    // Executes a reference run in SECCOMP learning mode (`scmp_learn_mode` of the guard must be
+    // set), the learned profile can be used as `scmp_profile_path` of further executions
+    public ProcExecResult LearnProfile(out string profileText)
+    {
+        if (!RuntimeInformation.IsOSPlatform(OSPlatform.Linux))
+            throw new PlatformNotSupportedException("Limtrac is available only on Linux!");
+        
+        ThrowIfNotReadyToExecute();
+        
+        var result = LimtracInterop.LearnProfile(_execProgInfo!, _execProgIo!, _execProgLimits!, _execProgGuard!, out var profileTextPtr);
+        
+        try { profileText = Marshal.PtrToStringUTF8(profileTextPtr) ?? string.Empty; }
+        finally { LimtracInterop.ProfileFree(profileTextPtr); }
+        
+        return result;
+    }
 }
\ No newline at end of file
@@ -17,4 +17,48 @@ internal static class LimtracInterop
         ExecProgLimits exec_prog_limits,
         ExecProgGuard  exec_prog_guard
     );
+
+    [DllImport("liblimtrac.so",
+        CallingConvention = CallingConvention.Cdecl,
+        EntryPoint = "limtrac_learn_profile")]
+    [SuppressMessage("ReSharper", "InconsistentNaming")]
+    internal static extern ProcExecResult LearnProfile(
+        ExecProgInfo   exec_prog_info,
+        ExecProgIO     exec_prog_io,
+        ExecProgLimits exec_prog_limits,
+        ExecProgGuard  exec_prog_guard,
+        out IntPtr     scmp_profile_text
+    );
+
+    [DllImport("liblimtrac.so",
+        CallingConvention = CallingConvention.Cdecl,
+        EntryPoint = "limtrac_profile_free")]
+    [SuppressMessage("ReSharper", "InconsistentNaming")]
+    internal static extern void ProfileFree(IntPtr scmp_profile_text);
+
+    [DllImport("liblimtrac.so",
+        CallingConvention = CallingConvention.Cdecl,
+        EntryPoint = "limtrac_policy_compile")]
+    [SuppressMessage("ReSharper", "InconsistentNaming")]
+    internal static extern LimtracPolicy PolicyCompile(
+        [MarshalAs(UnmanagedType.LPUTF8Str)] string exec_as_user,
+        ExecProgLimits exec_prog_limits,
+        ExecProgGuard  exec_prog_guard
+    );
+
+    [DllImport("liblimtrac.so",
+        CallingConvention = CallingConvention.Cdecl,
+        EntryPoint = "limtrac_policy_execute")]
+    [SuppressMessage("ReSharper", "InconsistentNaming")]
+    internal static extern ProcExecResult PolicyExecute(
+        LimtracPolicy exec_prog_policy,
+        ExecProgInfo  exec_prog_info,
+        ExecProgIO    exec_prog_io
+    );
+
+    [DllImport("liblimtrac.so",
+        CallingConvention = CallingConvention.Cdecl,
+        EntryPoint = "limtrac_policy_free")]
+    [SuppressMessage("ReSharper", "InconsistentNaming")]
+    internal static extern void PolicyFree(IntPtr exec_prog_policy);
 }
\ No newline at end of file
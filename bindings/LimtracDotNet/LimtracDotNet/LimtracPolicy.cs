@@ -0,0 +1,29 @@
+﻿using System.Diagnostics.CodeAnalysis;
+using System.Runtime.InteropServices;
+using Microsoft.Win32.SafeHandles;
+using Sirkadirov.Libraries.Limtrac.RequestStructs;
+
+namespace Sirkadirov.Libraries.Limtrac;
+
+// Compiled security policy, which can be shared by many executions (including concurrent
+// ones). Native policy is released when the handle is disposed or finalized.
+[SuppressMessage("ReSharper", "UnusedType.Global")]
+[SuppressMessage("ReSharper", "UnusedMember.Global")]
+public sealed class LimtracPolicy : SafeHandleZeroOrMinusOneIsInvalid
+{
+    private LimtracPolicy() : base(true) {  }
+
+    public static LimtracPolicy Compile(string execAsUser, ExecProgLimits execProgLimits, ExecProgGuard execProgGuard)
+    {
+        if (!RuntimeInformation.IsOSPlatform(OSPlatform.Linux))
+            throw new PlatformNotSupportedException("Limtrac is available only on Linux!");
+        
+        return LimtracInterop.PolicyCompile(execAsUser, execProgLimits, execProgGuard);
+    }
+
+    protected override bool ReleaseHandle()
+    {
+        LimtracInterop.PolicyFree(handle);
+        return true;
+    }
+}
\ No newline at end of file
@@ -3,13 +3,45 @@ using System.Runtime.InteropServices;
 
 namespace Sirkadirov.Libraries.Limtrac.RequestStructs;
 
-[StructLayout(LayoutKind.Sequential)]
+[StructLayout(LayoutKind.Sequential, CharSet = CharSet.Unicode)]
 [SuppressMessage("ReSharper", "MemberCanBePrivate.Global")]
 [SuppressMessage("ReSharper", "FieldCanBeMadeReadOnly.Global")]
 public struct ExecProgGuard
 {
     [MarshalAs(UnmanagedType.I1)] public bool scmp_enabled;
     [MarshalAs(UnmanagedType.I1)] public bool scmp_deny_common;
+    [MarshalAs(UnmanagedType.I4)] public int scmp_deny_tier;
+    [MarshalAs(UnmanagedType.I1)] public bool scmp_path_policy;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string scmp_path_allow = "";
+    [MarshalAs(UnmanagedType.I1)] public bool scmp_learn_mode;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string scmp_learn_path = "";
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string scmp_profile_path = "";
+    [MarshalAs(UnmanagedType.I1)] public bool scmp_deny_exec;
+    [MarshalAs(UnmanagedType.I1)] public bool scmp_allow_compat;
     [MarshalAs(UnmanagedType.I1)] public bool unshare_common;
     [MarshalAs(UnmanagedType.I1)] public bool unshare_network;
+    [MarshalAs(UnmanagedType.I4)] public int unshare_namespaces;
+    [MarshalAs(UnmanagedType.I4)] public int unshare_required;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string setns_join = "";
+    [MarshalAs(UnmanagedType.I1)] public bool net_loopback_up;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string mount_root_path = "";
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string mount_bind_ro = "";
+    [MarshalAs(UnmanagedType.I1)] public bool mount_nosuid;
+    [MarshalAs(UnmanagedType.I1)] public bool mount_nodev;
+    [MarshalAs(UnmanagedType.I1)] public bool mount_noexec;
+    [MarshalAs(UnmanagedType.I1)] public bool mount_tmpfs;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string mount_tmpfs_paths = "";
+    [MarshalAs(UnmanagedType.U8)] public ulong mount_tmpfs_size;
+    [MarshalAs(UnmanagedType.U8)] public ulong mount_tmpfs_inodes;
+    [MarshalAs(UnmanagedType.I1)] public bool mount_dev;
+    [MarshalAs(UnmanagedType.I1)] public bool landlock_enabled;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string landlock_read_paths = "";
+    [MarshalAs(UnmanagedType.I1)] public bool priv_no_new_privs;
+    [MarshalAs(UnmanagedType.I1)] public bool priv_drop_caps;
+    [MarshalAs(UnmanagedType.I1)] public bool priv_not_dumpable;
+    [MarshalAs(UnmanagedType.U4)] public uint uid_pool_first;
+    [MarshalAs(UnmanagedType.U4)] public uint uid_pool_size;
+    [MarshalAs(UnmanagedType.U8)] public ulong signals_ignored;
+
+    public ExecProgGuard() { }
 }
\ No newline at end of file
@@ -14,11 +14,30 @@ public struct ExecProgIO
     [MarshalAs(UnmanagedType.LPUTF8Str)] public string io_path_stdout = "";
     [MarshalAs(UnmanagedType.LPUTF8Str)] public string io_path_stderr = "";
     [MarshalAs(UnmanagedType.I1)]        public bool   io_dup_err_out;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string io_unix_sockets = "";
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string io_keep_fds = "";
+    [MarshalAs(UnmanagedType.I4)]        public int    io_fd_stdin;
+    [MarshalAs(UnmanagedType.I4)]        public int    io_fd_stdout;
+    [MarshalAs(UnmanagedType.I4)]        public int    io_fd_stderr;
+    [MarshalAs(UnmanagedType.LPUTF8Str)] public string io_fd_map = "";
+    [MarshalAs(UnmanagedType.I4)]        public int    io_mode_stdin;
+    [MarshalAs(UnmanagedType.I4)]        public int    io_mode_stdout;
+    [MarshalAs(UnmanagedType.I4)]        public int    io_mode_stderr;
+    [MarshalAs(UnmanagedType.I4)]        public int    io_open_policy;
+    [MarshalAs(UnmanagedType.U4)]        public uint   io_file_mode;
 
     public ExecProgIO()
     {
         io_redirected = false;
         io_dup_err_out = false;
+        io_fd_stdin = -1;
+        io_fd_stdout = -1;
+        io_fd_stderr = -1;
+        io_mode_stdin = Constants.IoModeAuto;
+        io_mode_stdout = Constants.IoModeAuto;
+        io_mode_stderr = Constants.IoModeAuto;
+        io_open_policy = Constants.IoOpenTruncate;
+        io_file_mode = 0;
     }
 }
 // ReSharper restore InconsistentNaming
\ No newline at end of file
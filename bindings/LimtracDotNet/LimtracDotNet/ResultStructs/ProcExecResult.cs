@@ -12,6 +12,13 @@ public struct ProcExecResult
     [MarshalAs(UnmanagedType.I4)] public int  exit_sign;
     [MarshalAs(UnmanagedType.I1)] public bool is_killed;
     [MarshalAs(UnmanagedType.I4)] public int  kill_reason;
+    [MarshalAs(UnmanagedType.U8)] public ulong scmp_denied;
+    [MarshalAs(UnmanagedType.I4)] public int  setup_failure;
+    [MarshalAs(UnmanagedType.I4)] public int  setup_errno;
+    [MarshalAs(UnmanagedType.I4)] public int  landlock_abi;
+    [MarshalAs(UnmanagedType.U8)] public ulong landlock_access;
+    [MarshalAs(UnmanagedType.I4)] public int  unshare_created;
+    [MarshalAs(UnmanagedType.I1)] public bool group_leftover;
     
     [MarshalAs(UnmanagedType.Struct)] public ProcResUsage res_usage;
 }
\ No newline at end of file
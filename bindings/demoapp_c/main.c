@@ -18,8 +18,10 @@ int main()
             execProgGuard);
 
     // Print execution result
-    printf("Exit code:\t%d\r\nExit signal:\t%d\r\nIs killed:\t%d\r\nKill reason:\t%d\r\n",
-           execResult.exit_code, execResult.exit_sign, execResult.is_killed, execResult.kill_reason);
+    printf("Exit code:\t%d\r\nExit signal:\t%d\r\nIs killed:\t%d\r\nKill reason:\t%d\r\nDenied calls:\t%llu\r\nSetup failure:\t%d (errno %d)\r\nLandlock:\tABI %d (access 0x%llx)\r\nNamespaces:\t0x%x\r\nGroup leftover:\t%d\r\n",
+           execResult.exit_code, execResult.exit_sign, execResult.is_killed, execResult.kill_reason, execResult.scmp_denied,
+           execResult.setup_failure, execResult.setup_errno, execResult.landlock_abi, execResult.landlock_access,
+           execResult.unshare_created, execResult.group_leftover);
     printf("\r\n");
     // Print resources usage
     printf("Exec time:\t%llu\r\nProc time:\t%llu\r\nMax RSS:\t%llu\r\n",
@@ -46,6 +48,17 @@ ExecProgIO get_exec_prog_io()
     execProgIo.io_path_stdout = "./out.dat";
     execProgIo.io_path_stderr = "";
     execProgIo.io_dup_err_out = true;
+    execProgIo.io_unix_sockets = "";
+    execProgIo.io_keep_fds = "";
+    execProgIo.io_fd_stdin = -1;
+    execProgIo.io_fd_stdout = -1;
+    execProgIo.io_fd_stderr = -1;
+    execProgIo.io_fd_map = "";
+    execProgIo.io_mode_stdin = IO_MODE_AUTO;
+    execProgIo.io_mode_stdout = IO_MODE_AUTO;
+    execProgIo.io_mode_stderr = IO_MODE_AUTO;
+    execProgIo.io_open_policy = IO_OPEN_TRUNCATE;
+    execProgIo.io_file_mode = 0640;
     return execProgIo;
 }
 
@@ -64,7 +77,37 @@ ExecProgGuard get_exec_prog_guard()
     ExecProgGuard execProgGuard;
     execProgGuard.scmp_enabled     = true;
     execProgGuard.scmp_deny_common = true;
+    execProgGuard.scmp_deny_tier   = SCMP_DENY_TIER_HARDENED;
+    execProgGuard.scmp_path_policy = false;
+    execProgGuard.scmp_path_allow  = "";
+    execProgGuard.scmp_learn_mode  = false;
+    execProgGuard.scmp_learn_path  = "";
+    execProgGuard.scmp_profile_path = "";
+    execProgGuard.scmp_deny_exec   = true;
+    execProgGuard.scmp_allow_compat = false;
     execProgGuard.unshare_common   = true;
     execProgGuard.unshare_network  = true;
+    execProgGuard.unshare_namespaces = UNSHARE_NS_TIME;
+    execProgGuard.unshare_required = 0;
+    execProgGuard.setns_join       = "";
+    execProgGuard.net_loopback_up  = true;
+    execProgGuard.mount_root_path  = "";
+    execProgGuard.mount_bind_ro    = "";
+    execProgGuard.mount_nosuid     = true;
+    execProgGuard.mount_nodev      = true;
+    execProgGuard.mount_noexec     = false;
+    execProgGuard.mount_tmpfs      = true;
+    execProgGuard.mount_tmpfs_paths = "";
+    execProgGuard.mount_tmpfs_size = 64 * 1000000; // 64 MB
+    execProgGuard.mount_tmpfs_inodes = 1024;
+    execProgGuard.mount_dev        = true;
+    execProgGuard.landlock_enabled = false;
+    execProgGuard.landlock_read_paths = "/usr:/etc:/dev";
+    execProgGuard.priv_no_new_privs = true;
+    execProgGuard.priv_drop_caps   = true;
+    execProgGuard.priv_not_dumpable = true;
+    execProgGuard.uid_pool_first   = 0;
+    execProgGuard.uid_pool_size    = 0;
+    execProgGuard.signals_ignored  = 0;
     return execProgGuard;
 }
\ No newline at end of file
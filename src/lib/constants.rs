@@ -16,7 +16,7 @@
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use libc::c_int;
+use libc::{c_int, c_uint};
 
 /// cbindgen:ignore
 pub const SYS_EXEC_FAILED : c_int = -1;
@@ -24,6 +24,8 @@ pub const SYS_EXEC_FAILED : c_int = -1;
 pub const SYS_EXEC_OK: c_int = 0;
 /// cbindgen:ignore
 pub const TIME_MULTIPLIER : c_int = 1000;
+/// cbindgen:ignore
+pub const USER_NS_NOBODY_ID : u32 = 65534;
 
 /*
  * Child process kill reasons, used to fill the
@@ -35,4 +37,75 @@ pub const KILL_REASON_NONE: c_int = 0;
 pub const KILL_REASON_SECURITY : c_int = 1;
 pub const KILL_REASON_REALTIME : c_int = 2;
 pub const KILL_REASON_PROCTIME : c_int = 3;
-pub const KILL_REASON_PROCWSET : c_int = 4;
\ No newline at end of file
+pub const KILL_REASON_PROCWSET : c_int = 4;
+
+/*
+ * Child process setup stages that may fail before the program
+ * is executed, used to fill the `setup_failure` field of the
+ * `ProcExecResult` struct (along with the `setup_errno` field).
+ */
+
+pub const SETUP_FAILURE_NONE : c_int = 0;
+pub const SETUP_FAILURE_MOUNT : c_int = 1;
+pub const SETUP_FAILURE_LANDLOCK : c_int = 2;
+pub const SETUP_FAILURE_CREDENTIALS : c_int = 3;
+pub const SETUP_FAILURE_PRIVILEGES : c_int = 4;
+pub const SETUP_FAILURE_NAMESPACES : c_int = 5;
+pub const SETUP_FAILURE_NETWORK : c_int = 6;
+pub const SETUP_FAILURE_IO_STREAMS : c_int = 7;
+pub const SETUP_FAILURE_UID_POOL : c_int = 8;
+
+/*
+ * Redirection modes of standard streams, used to fill the `io_mode_stdin`,
+ * `io_mode_stdout` and `io_mode_stderr` fields of `ExecProgIO` struct. In
+ * auto mode, a stream is taken from a descriptor or a path, if any is given,
+ * otherwise it is redirected to `/dev/null`.
+ */
+
+pub const IO_MODE_AUTO : c_int = 0;
+pub const IO_MODE_FILE : c_int = 1;
+pub const IO_MODE_NULL : c_int = 2;
+pub const IO_MODE_INHERIT : c_int = 3;
+pub const IO_MODE_PIPE : c_int = 4;
+pub const IO_MODE_APPEND : c_int = 5;
+
+/*
+ * Policies of opening output files, used to fill the `io_open_policy`
+ * field of `ExecProgIO` struct. Output files are never opened through
+ * a symbolic link, and are created with the `io_file_mode` permissions
+ * (or `IO_FILE_MODE_DEFAULT`, if it is zero).
+ */
+
+pub const IO_OPEN_TRUNCATE : c_int = 0;
+pub const IO_OPEN_APPEND : c_int = 1;
+pub const IO_OPEN_EXCLUSIVE : c_int = 2;
+/// cbindgen:ignore
+pub const IO_FILE_MODE_DEFAULT : c_uint = 0o644;
+
+/*
+ * Namespaces to unshare, used as bits of the `unshare_namespaces` and
+ * `unshare_required` fields of `ExecProgGuard` struct, and of the
+ * `unshare_created` field of `ProcExecResult` struct.
+ */
+
+pub const UNSHARE_NS_MOUNT : c_int = 1;
+pub const UNSHARE_NS_IPC : c_int = 1 << 1;
+pub const UNSHARE_NS_UTS : c_int = 1 << 2;
+pub const UNSHARE_NS_PID : c_int = 1 << 3;
+pub const UNSHARE_NS_NET : c_int = 1 << 4;
+pub const UNSHARE_NS_CGROUP : c_int = 1 << 5;
+pub const UNSHARE_NS_USER : c_int = 1 << 6;
+pub const UNSHARE_NS_TIME : c_int = 1 << 7;
+/// cbindgen:ignore
+pub const UNSHARE_NS_ALL : c_int = (1 << 8) - 1;
+/// cbindgen:ignore
+pub const UNSHARE_NS_COMMON : c_int = UNSHARE_NS_MOUNT | UNSHARE_NS_IPC | UNSHARE_NS_UTS | UNSHARE_NS_PID | UNSHARE_NS_CGROUP;
+
+/*
+ * Tiers of system calls denylist, used to fill the `scmp_deny_tier`
+ * field of `ExecProgGuard` struct. Each tier includes previous ones.
+ */
+
+pub const SCMP_DENY_TIER_BASELINE : c_int = 0;
+pub const SCMP_DENY_TIER_HARDENED : c_int = 1;
+pub const SCMP_DENY_TIER_PARANOID : c_int = 2;
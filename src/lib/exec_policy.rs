@@ -0,0 +1,89 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::{CStr, CString};
+use libc::{c_char, c_int, gid_t, uid_t};
+use crate::{ExecProgGuard, ExecProgLimits};
+use crate::seccomp_filters::ScmpFilters;
+
+/*
+ * Security policy, compiled once and shared by many executions (including concurrent ones):
+ * SECCOMP filters compiled to BPF, namespaces to unshare, resource limits and credentials.
+ * A policy is immutable, so executions can't affect each other through it.
+ */
+pub struct ExecProgPolicy
+{
+    pub(crate) exec_as_ids      : Option<(uid_t, gid_t)>, // Resolved user and group to execute programs as
+    pub(crate) exec_prog_limits : ExecProgLimits,
+    pub(crate) exec_prog_guard  : ExecProgGuard,       // Points to strings owned by the policy
+    pub(crate) scmp_filters     : Option<ScmpFilters>,
+    pub(crate) landlock_abi     : c_int,               // Landlock ABI version, if it is enabled
+    pub(crate) setns_namespaces : c_int,               // Types of namespaces to join, see `UNSHARE_NS_*`
+    _guard_strings              : [CString; 8]
+}
+
+// Raw pointers of the guard point to strings owned by the policy, which are never modified
+unsafe impl Send for ExecProgPolicy {}
+unsafe impl Sync for ExecProgPolicy {}
+
+impl ExecProgPolicy
+{
+    pub fn compile(exec_as_user : &CStr, exec_prog_limits : &ExecProgLimits, exec_prog_guard : &ExecProgGuard) -> Self
+    {
+        // Verify data contained in `ExecProgGuard` struct
+        if !exec_prog_guard.verify()
+        { panic!("ExecProgGuard struct contains invalid data!"); }
+
+        // Programs are executed as a leased user, if the pool is enabled
+        if exec_prog_guard.uid_pool_size > 0 && !exec_as_user.to_bytes().is_empty()
+        { panic!("ExecProgGuard struct contains invalid data!"); }
+
+        // Strings of the request are owned by the caller, so we copy them
+        let guard_strings = [exec_prog_guard.scmp_path_allow, exec_prog_guard.scmp_learn_path,
+                             exec_prog_guard.scmp_profile_path, exec_prog_guard.mount_root_path,
+                             exec_prog_guard.mount_bind_ro, exec_prog_guard.mount_tmpfs_paths,
+                             exec_prog_guard.landlock_read_paths, exec_prog_guard.setns_join].map(copy_c_string);
+
+        let mut exec_prog_guard = *exec_prog_guard;
+        exec_prog_guard.scmp_path_allow = guard_strings[0].as_ptr();
+        exec_prog_guard.scmp_learn_path = guard_strings[1].as_ptr();
+        exec_prog_guard.scmp_profile_path = guard_strings[2].as_ptr();
+        exec_prog_guard.mount_root_path = guard_strings[3].as_ptr();
+        exec_prog_guard.mount_bind_ro = guard_strings[4].as_ptr();
+        exec_prog_guard.mount_tmpfs_paths = guard_strings[5].as_ptr();
+        exec_prog_guard.landlock_read_paths = guard_strings[6].as_ptr();
+        exec_prog_guard.setns_join = guard_strings[7].as_ptr();
+
+        Self {
+            exec_as_ids: crate::sandboxing_features::get_user_ids(exec_as_user),
+            exec_prog_limits: *exec_prog_limits,
+            scmp_filters: exec_prog_guard.scmp_enabled.then(|| ScmpFilters::build(&exec_prog_guard)),
+            setns_namespaces: exec_prog_guard.get_setns_namespaces().unwrap_or_default(),
+            landlock_abi: if exec_prog_guard.landlock_enabled { crate::landlock::get_abi_version() } else { 0 },
+            exec_prog_guard,
+            _guard_strings: guard_strings
+        }
+    }
+}
+
+fn copy_c_string(string_ptr : *const c_char) -> CString
+{
+    // SECCOMP strings are not checked if it is disabled
+    if string_ptr.is_null() { return CString::default(); }
+    unsafe { CStr::from_ptr(string_ptr) }.to_owned()
+}
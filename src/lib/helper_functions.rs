@@ -26,4 +26,19 @@ macro_rules! panic_on_syscall {
         panic!("System call '{}' failed with 'ERRNO = {}'!", $($syscall_name)*, nix::errno::errno());
     };
 }
-pub(crate) use panic_on_syscall;
\ No newline at end of file
+pub(crate) use panic_on_syscall;
+
+/*
+ * Moves a descriptor to the lowest free number not below `fd_min`, so it doesn't get
+ * replaced by descriptors mapped into the child process. Moved descriptor is closed on exec.
+ */
+pub(crate) fn move_fd_above(fd : libc::c_int, fd_min : libc::c_int) -> libc::c_int
+{
+    if fd >= fd_min { return fd; }
+
+    let moved_fd = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, fd_min) };
+    if moved_fd == crate::SYS_EXEC_FAILED { panic_on_syscall!("fcntl"); }
+
+    unsafe { libc::close(fd) };
+    moved_fd
+}
@@ -0,0 +1,159 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use libc::{c_int, c_void};
+use crate::{ExecProgGuard, ExecProgInfo, ExecProgIO, SYS_EXEC_FAILED};
+use crate::constants::SETUP_FAILURE_LANDLOCK;
+use crate::setup_channel::SetupChannel;
+
+/*
+ * Kernel ABI definitions of the Landlock LSM, see `linux/landlock.h`.
+ * They are not yet exported by the `libc` crate version we use.
+ */
+
+const LANDLOCK_CREATE_RULESET_VERSION : u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH : c_int = 1;
+
+const LANDLOCK_ACCESS_FS_EXECUTE : u64 = 1 << 0;
+const LANDLOCK_ACCESS_FS_WRITE_FILE : u64 = 1 << 1;
+const LANDLOCK_ACCESS_FS_READ_FILE : u64 = 1 << 2;
+const LANDLOCK_ACCESS_FS_READ_DIR : u64 = 1 << 3;
+const LANDLOCK_ACCESS_FS_ABI_1 : u64 = (1 << 13) - 1;   // Includes removing and creating files of all types
+const LANDLOCK_ACCESS_FS_REFER : u64 = 1 << 13;         // Since ABI version 2
+const LANDLOCK_ACCESS_FS_TRUNCATE : u64 = 1 << 14;      // Since ABI version 3
+const LANDLOCK_ACCESS_FS_IOCTL_DEV : u64 = 1 << 15;     // Since ABI version 5
+
+// Only these access rights can be granted for files, the rest are related to directories
+const LANDLOCK_ACCESS_FS_FILE : u64 = LANDLOCK_ACCESS_FS_EXECUTE | LANDLOCK_ACCESS_FS_WRITE_FILE
+    | LANDLOCK_ACCESS_FS_READ_FILE | LANDLOCK_ACCESS_FS_TRUNCATE | LANDLOCK_ACCESS_FS_IOCTL_DEV;
+
+#[repr(C)]
+struct LandlockRulesetAttr
+{
+    handled_access_fs : u64
+}
+
+#[repr(C, packed)]
+struct LandlockPathBeneathAttr
+{
+    allowed_access : u64,
+    parent_fd      : c_int
+}
+
+/* @Returns the Landlock ABI version supported by the kernel, or zero if it is not available */
+pub fn get_abi_version() -> c_int
+{
+    let abi_version = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<c_void>(),
+                                             0, LANDLOCK_CREATE_RULESET_VERSION) };
+    if abi_version > 0 { abi_version as c_int } else { 0 }
+}
+
+/*
+ * Access rights unknown to the kernel can't be handled, so newer ones are dropped on older
+ * kernels. This means that actions like truncating files stay allowed there, which is
+ * still better than no restrictions at all. The result reports rights that were handled.
+ */
+pub fn get_handled_access(abi_version : c_int) -> u64
+{
+    let mut handled_access = if abi_version >= 1 { LANDLOCK_ACCESS_FS_ABI_1 } else { 0 };
+
+    if abi_version >= 2 { handled_access |= LANDLOCK_ACCESS_FS_REFER; }
+    if abi_version >= 3 { handled_access |= LANDLOCK_ACCESS_FS_TRUNCATE; }
+    if abi_version >= 5 { handled_access |= LANDLOCK_ACCESS_FS_IOCTL_DEV; }
+
+    handled_access
+}
+
+/*
+ * [CHILD] Restricts filesystem access of the program. Configured paths and the program
+ * itself can be read and executed only, while the working directory (current one at this
+ * point) can be modified in any way, and output files can be written. Files opened before,
+ * like redirected I/O streams, are not affected. Missing paths are skipped.
+ */
+pub fn init_landlock(exec_prog_info : &ExecProgInfo, exec_prog_io : &ExecProgIO, exec_prog_guard : &ExecProgGuard,
+                     abi_version : c_int, setup_channel : &SetupChannel)
+{
+    if !exec_prog_guard.landlock_enabled || abi_version == 0 { return; }
+
+    let handled_access = get_handled_access(abi_version);
+    let read_access = handled_access & (LANDLOCK_ACCESS_FS_EXECUTE | LANDLOCK_ACCESS_FS_READ_FILE | LANDLOCK_ACCESS_FS_READ_DIR);
+    let write_access = handled_access & (LANDLOCK_ACCESS_FS_WRITE_FILE | LANDLOCK_ACCESS_FS_TRUNCATE);
+
+    let ruleset_attr = LandlockRulesetAttr { handled_access_fs: handled_access };
+    let ruleset_fd = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, &ruleset_attr,
+                                            size_of_val(&ruleset_attr), 0) } as c_int;
+    if ruleset_fd == SYS_EXEC_FAILED { setup_channel.report_failure(SETUP_FAILURE_LANDLOCK); }
+
+    let program_path = PathBuf::from(unsafe { CStr::from_ptr(exec_prog_info.program_path) }.to_str().unwrap());
+    let mut path_rules : Vec<(PathBuf, u64)> = exec_prog_guard.get_landlock_read_vec().into_iter()
+        .chain([program_path])
+        .map(|path| (path, read_access))
+        .collect();
+
+    path_rules.push((PathBuf::from("."), handled_access));
+
+    if exec_prog_io.io_redirected
+    {
+        for io_path in [exec_prog_io.io_path_stdout, exec_prog_io.io_path_stderr]
+        {
+            let io_path = unsafe { CStr::from_ptr(io_path) }.to_str().unwrap();
+            if !io_path.is_empty() { path_rules.push((PathBuf::from(io_path), write_access)); }
+        }
+    }
+
+    for (path, allowed_access) in path_rules
+    {
+        if !add_path_rule(ruleset_fd, &path, allowed_access)
+        { setup_channel.report_failure(SETUP_FAILURE_LANDLOCK); }
+    }
+
+    // Unprivileged processes must set `no_new_privs` before restricting themselves
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == SYS_EXEC_FAILED
+            || libc::syscall(libc::SYS_landlock_restrict_self, ruleset_fd, 0) == SYS_EXEC_FAILED as i64
+        { setup_channel.report_failure(SETUP_FAILURE_LANDLOCK); }
+
+        libc::close(ruleset_fd);
+    }
+}
+
+/* @Adds a rule for a file or directory, returns `false` on failure (but not if the path is missing) */
+fn add_path_rule(ruleset_fd : c_int, path : &Path, allowed_access : u64) -> bool
+{
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+    let parent_fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if parent_fd == SYS_EXEC_FAILED { return true; }
+
+    // Directory-related access rights can't be granted for files
+    let mut path_stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    let is_dir = unsafe { libc::fstat(parent_fd, path_stat.as_mut_ptr()) } != SYS_EXEC_FAILED
+        && unsafe { path_stat.assume_init() }.st_mode & libc::S_IFMT == libc::S_IFDIR;
+
+    let path_beneath_attr = LandlockPathBeneathAttr {
+        allowed_access: if is_dir { allowed_access } else { allowed_access & LANDLOCK_ACCESS_FS_FILE },
+        parent_fd
+    };
+
+    let result = unsafe { libc::syscall(libc::SYS_landlock_add_rule, ruleset_fd, LANDLOCK_RULE_PATH_BENEATH,
+                                        &path_beneath_attr, 0) };
+    unsafe { libc::close(parent_fd) };
+
+    result != SYS_EXEC_FAILED as i64
+}
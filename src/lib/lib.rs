@@ -18,19 +18,55 @@
 
 use std::ffi::{CStr, CString};
 use std::mem::MaybeUninit;
+use std::path::{Path, PathBuf};
 use std::time;
 use std::time::SystemTime;
-use libc::{c_int, c_ulonglong, pid_t};
+use libc::{c_char, c_int, c_ulonglong, gid_t, pid_t, uid_t};
 
 mod constants;
+mod exec_policy;
 mod sandboxing_features;
 mod helper_functions;
+mod landlock;
+mod mount_layout;
+mod network;
+mod privileges;
 mod request_structs;
 mod result_structs;
+mod seccomp_bpf;
+#[cfg(feature = "native-bpf")]
+mod seccomp_compiler;
+#[cfg(not(feature = "native-bpf"))]
+mod seccomp_context;
+mod seccomp_filters;
+mod seccomp_notify;
+mod seccomp_profile;
+mod seccomp_tiers;
+mod setup_channel;
+#[cfg(feature = "native-bpf")]
+mod syscall_tables;
+mod uid_pool;
+
+// SECCOMP contexts are compiled either by libseccomp, or by our own BPF compiler
+#[cfg(feature = "native-bpf")]
+use crate::seccomp_compiler as seccomp_context;
+#[cfg(not(any(feature = "native-bpf", feature = "libseccomp")))]
+compile_error!("Either `libseccomp` or `native-bpf` feature must be enabled!");
 
 pub use crate::constants::{KILL_REASON_NONE, KILL_REASON_PROCTIME, KILL_REASON_PROCWSET, KILL_REASON_REALTIME, KILL_REASON_SECURITY, SYS_EXEC_FAILED, SYS_EXEC_OK};
+pub use crate::constants::{SETUP_FAILURE_CREDENTIALS, SETUP_FAILURE_IO_STREAMS, SETUP_FAILURE_LANDLOCK, SETUP_FAILURE_MOUNT, SETUP_FAILURE_NAMESPACES, SETUP_FAILURE_NETWORK, SETUP_FAILURE_NONE, SETUP_FAILURE_PRIVILEGES, SETUP_FAILURE_UID_POOL};
+pub use crate::constants::{IO_MODE_APPEND, IO_MODE_AUTO, IO_MODE_FILE, IO_MODE_INHERIT, IO_MODE_NULL, IO_MODE_PIPE};
+pub use crate::constants::{IO_OPEN_APPEND, IO_OPEN_EXCLUSIVE, IO_OPEN_TRUNCATE};
+pub use crate::constants::{UNSHARE_NS_CGROUP, UNSHARE_NS_IPC, UNSHARE_NS_MOUNT, UNSHARE_NS_NET, UNSHARE_NS_PID, UNSHARE_NS_TIME, UNSHARE_NS_USER, UNSHARE_NS_UTS};
+pub use crate::constants::{SCMP_DENY_TIER_BASELINE, SCMP_DENY_TIER_HARDENED, SCMP_DENY_TIER_PARANOID};
+pub use crate::exec_policy::ExecProgPolicy;
 pub use crate::request_structs::{ExecProgGuard, ExecProgInfo, ExecProgIO, ExecProgLimits};
 pub use crate::result_structs::ProcExecResult;
+pub use crate::seccomp_profile::{SyscallProfile, SyscallProfileEntry};
+pub use crate::seccomp_tiers::{get_deny_rules, DenyRule};
+use crate::seccomp_notify::NotifyChannel;
+use crate::setup_channel::SetupChannel;
+use crate::uid_pool::UidLease;
 
 //noinspection ALL
 #[no_mangle]
@@ -41,30 +77,181 @@ pub extern "C" fn limtrac_execute(
     exec_prog_guard  : ExecProgGuard
 ) -> ProcExecResult
 {
-    // Verify data contained in `ExecProgInfo` struct
+    // Requests are verified before the policy, which resolves `exec_as_user`, is compiled
+    verify_requests(&exec_prog_info, &exec_prog_io);
+
+    let exec_as_user = unsafe { CStr::from_ptr(exec_prog_info.exec_as_user) };
+    let exec_prog_policy = ExecProgPolicy::compile(exec_as_user, &exec_prog_limits, &exec_prog_guard);
+
+    execute_verified(&exec_prog_info, &exec_prog_io, &exec_prog_policy).0
+}
+
+/*
+ * Compiles a security policy, which can be used by many executions of programs, including
+ * concurrent ones. The handle must be released using `limtrac_policy_free` when all of
+ * executions, referencing it, are finished.
+ */
+//noinspection ALL
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn limtrac_policy_compile(
+    exec_as_user     : *const c_char,
+    exec_prog_limits : ExecProgLimits,
+    exec_prog_guard  : ExecProgGuard
+) -> *const ExecProgPolicy
+{
+    if exec_as_user.is_null()
+    { panic!("Username of the policy must not be null!"); }
+
+    let exec_as_user = unsafe { CStr::from_ptr(exec_as_user) };
+    Box::into_raw(Box::new(ExecProgPolicy::compile(exec_as_user, &exec_prog_limits, &exec_prog_guard)))
+}
+
+/*
+ * Executes a program using a compiled policy. User to execute the program as is a part
+ * of the policy, so `exec_as_user` field of `ExecProgInfo` struct must be empty.
+ */
+//noinspection ALL
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn limtrac_policy_execute(
+    exec_prog_policy : *const ExecProgPolicy,
+    exec_prog_info   : ExecProgInfo,
+    exec_prog_io     : ExecProgIO
+) -> ProcExecResult
+{
+    let exec_prog_policy = match unsafe { exec_prog_policy.as_ref() } {
+        Some(exec_prog_policy) => exec_prog_policy,
+        None => { panic!("Policy handle must not be null!") }
+    };
+
+    verify_requests(&exec_prog_info, &exec_prog_io);
+
+    if !unsafe { CStr::from_ptr(exec_prog_info.exec_as_user) }.to_bytes().is_empty()
+    { panic!("ExecProgInfo struct contains invalid data!"); }
+
+    execute_verified(&exec_prog_info, &exec_prog_io, exec_prog_policy).0
+}
+
+//noinspection ALL
+#[no_mangle]
+pub extern "C" fn limtrac_policy_free(exec_prog_policy : *const ExecProgPolicy)
+{
+    if !exec_prog_policy.is_null()
+    { drop(unsafe { Box::from_raw(exec_prog_policy as *mut ExecProgPolicy) }); }
+}
+
+/*
+ * Executes a reference run of the program in SECCOMP learning mode. Learned profile is saved
+ * to `scmp_learn_path` (if it is specified), so it can be used as `scmp_profile_path` of further
+ * executions, and its text is stored to `scmp_profile_text` (unless it is null), which must be
+ * released using `limtrac_profile_free`.
+ */
+//noinspection ALL
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn limtrac_learn_profile(
+    exec_prog_info    : ExecProgInfo,
+    exec_prog_io      : ExecProgIO,
+    exec_prog_limits  : ExecProgLimits,
+    exec_prog_guard   : ExecProgGuard,
+    scmp_profile_text : *mut *mut c_char
+) -> ProcExecResult
+{
+    if !exec_prog_guard.scmp_learn_mode
+    { panic!("ExecProgGuard struct must enable SECCOMP learning mode!"); }
+
+    verify_requests(&exec_prog_info, &exec_prog_io);
+
+    let exec_as_user = unsafe { CStr::from_ptr(exec_prog_info.exec_as_user) };
+    let exec_prog_policy = ExecProgPolicy::compile(exec_as_user, &exec_prog_limits, &exec_prog_guard);
+
+    let (execution_result, scmp_profile) = execute_verified(&exec_prog_info, &exec_prog_io, &exec_prog_policy);
+
+    if !scmp_profile_text.is_null()
+    {
+        let profile_text = CString::new(scmp_profile.unwrap_or_default().to_text()).unwrap();
+        unsafe { *scmp_profile_text = profile_text.into_raw() };
+    }
+
+    execution_result
+}
+
+//noinspection ALL
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn limtrac_profile_free(scmp_profile_text : *mut c_char)
+{
+    if !scmp_profile_text.is_null()
+    { drop(unsafe { CString::from_raw(scmp_profile_text) }); }
+}
+
+fn verify_requests(exec_prog_info : &ExecProgInfo, exec_prog_io : &ExecProgIO)
+{
+    // Verify data contained in `ExecProgInfo` struct (including null pointers)
     if !exec_prog_info.verify()
     { panic!("ExecProgInfo struct contains invalid data!"); }
 
     // Verify data contained in `ExecProgIO` struct
     if !exec_prog_io.verify()
     { panic!("ExecProgIO struct contains invalid data!"); }
+}
+
+/* Executes a program, requests must be checked by `verify_requests` before */
+fn execute_verified(
+    exec_prog_info   : &ExecProgInfo,
+    exec_prog_io     : &ExecProgIO,
+    exec_prog_policy : &ExecProgPolicy
+) -> (ProcExecResult, Option<SyscallProfile>)
+{
+    let (execution_result, scmp_profile) = execute_internal(exec_prog_info, exec_prog_io, exec_prog_policy);
+
+    // Save a learned profile, if requested by the caller
+    if let Some(scmp_profile) = scmp_profile.as_ref()
+    { seccomp_profile::save_requested_profile(scmp_profile, exec_prog_policy.exec_prog_guard.scmp_learn_path); }
 
-    execute_internal(&exec_prog_info, &exec_prog_io, &exec_prog_limits, &exec_prog_guard)
+    (execution_result, scmp_profile)
 }
 
 //noinspection ALL
 fn execute_internal(
     exec_prog_info   : &ExecProgInfo,
     exec_prog_io     : &ExecProgIO,
-    exec_prog_limits : &ExecProgLimits,
-    exec_prog_guard  : &ExecProgGuard
-) -> ProcExecResult
+    exec_prog_policy : &ExecProgPolicy
+) -> (ProcExecResult, Option<SyscallProfile>)
 {
+    let exec_prog_limits = &exec_prog_policy.exec_prog_limits;
+    let exec_prog_guard = &exec_prog_policy.exec_prog_guard;
+
     /*
      * Try to create a new child process based on the current one, so we
      * can control everything about it in the parent (current) process.
      */
 
+    // Lease a user of the pool, which is released after all of its processes are killed
+    let uid_lease = match UidLease::acquire(exec_prog_info, exec_prog_guard)
+    {
+        Ok(uid_lease) => uid_lease,
+        Err(setup_errno) =>
+        {
+            let mut execution_result = ProcExecResult::new();
+            execution_result.setup_failure = SETUP_FAILURE_UID_POOL;
+            execution_result.setup_errno = setup_errno;
+            return (execution_result, None);
+        }
+    };
+    let exec_as_ids = uid_lease.as_ref().map(UidLease::get_ids).or(exec_prog_policy.exec_as_ids);
+
+    // Descriptors used by the child process during setup must not be replaced by mapped ones
+    let fd_min = exec_prog_io.get_fd_map_vec().iter().map(|&(_, child_fd)| child_fd + 1).max().unwrap_or(0);
+
+    // Create a channel to receive SECCOMP notifications listener from the child process
+    let notify_channel = if exec_prog_guard.scmp_path_policy || exec_prog_guard.scmp_learn_mode
+        || exec_prog_guard.scmp_deny_exec { Some(NotifyChannel::new(exec_prog_guard, fd_min)) } else { None };
+
+    // Create a channel to receive a failed setup stage from the child process
+    let mut setup_channel = SetupChannel::new(fd_min);
+
     // Try to fork (try to create a child process)
     let child_pid = unsafe { libc::fork() };
     let child_time_start = SystemTime::now();
@@ -83,13 +270,30 @@ fn execute_internal(
     if child_pid == 0
     {
         // We are in a child process right now, so we can execute whatever we want
-        exec_child_cmd(exec_prog_info, exec_prog_io, exec_prog_limits,
-                       exec_prog_guard, exec_path_normal, exec_argv_normal);
+        exec_child_cmd(exec_prog_info, exec_prog_io, exec_prog_policy, exec_as_ids,
+                       notify_channel.as_ref(), &setup_channel, exec_argv_normal);
     }
     /* ===== /[CHILD] PROCESS CODE FRAGMENT ===== */
 
+    setup_channel.close_sender();
+
     /* ===== [PARENT] PROCESS CODE FRAGMENT ===== */
 
+    /*
+     * [Supervisor]
+     */
+
+    let mut notify_supervisor = notify_channel.and_then(|notify_channel| {
+        let allow_list = if exec_prog_guard.scmp_path_policy {
+            // Target program must be allowed to start, even if it is not in the allowed paths list
+            let mut allow_list = exec_prog_guard.get_path_allow_vec();
+            allow_list.push(get_exec_path_absolute(exec_prog_info, exec_path_normal));
+            Some(allow_list)
+        } else { None };
+
+        notify_channel.accept_listener(exec_prog_guard, allow_list, child_pid)
+    });
+
     /*
      * [Watchdog]
      */
@@ -97,45 +301,58 @@ fn execute_internal(
     let mut execution_result  : ProcExecResult = ProcExecResult::new();
     let     loop_exec_timeout : time::Duration = std::time::Duration::from_millis(50);
 
+    // Landlock is enforced by the child process, unless it reports a failure
+    execution_result.landlock_abi = exec_prog_policy.landlock_abi;
+    execution_result.landlock_access = landlock::get_handled_access(exec_prog_policy.landlock_abi);
+
+    // In a new PID namespace, the program is a grandchild of the child process
+    let pid_namespace = (exec_prog_guard.get_unshare_namespaces() | exec_prog_policy.setns_namespaces) & UNSHARE_NS_PID != 0;
+    let mut program_pid : Option<pid_t> = if pid_namespace { None } else { Some(child_pid) };
+
     loop {
-        // Use MaybeUninit to initialize variables used by `wait4` system call
-        let mut waitpid_status = MaybeUninit::<c_int>::uninit();
-        let mut waitpid_rusage = MaybeUninit::<libc::rusage>::uninit();
+        // Child process is not reaped yet (WNOWAIT), so its process group ID can't be reused
+        let mut waitid_info = MaybeUninit::<libc::siginfo_t>::zeroed();
 
-        let waitpid_result = unsafe { libc::wait4(child_pid,waitpid_status.as_mut_ptr(),
-                                                  libc::WNOHANG, waitpid_rusage.as_mut_ptr()) };
+        let waitid_result = unsafe { libc::waitid(libc::P_PID, child_pid as libc::id_t, waitid_info.as_mut_ptr(),
+                                                  libc::WEXITED | libc::WNOHANG | libc::WNOWAIT) };
 
-        // Panic on `wait4` system call execution error
-        if waitpid_result == SYS_EXEC_FAILED
-        { crate::helper_functions::panic_on_syscall!("wait4"); }
+        // Panic on `waitid` system call execution error
+        if waitid_result == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("waitid"); }
 
         // Get the child process execution period in milliseconds
         execution_result.res_usage.real_time = child_time_start.elapsed().unwrap().as_millis() as c_ulonglong;
 
-        let waitpid_status = unsafe { waitpid_status.assume_init() };
-        let waitpid_rusage = unsafe { waitpid_rusage.assume_init() };
+        let child_exited = unsafe { waitid_info.assume_init().si_pid() } != 0;
 
         /* ===== @On child process [executing] ===== */
-        if waitpid_result == 0 {
+        if !child_exited {
+
+            // Fetch counters' values from the processes `stat` file, while the program is
+            // not started yet (the child process still sets up its environment), only limits
+            // not depending on them are enforced
+            if program_pid.is_none() { program_pid = setup_channel.receive_report().program_pid; }
+            let stat_loaded = program_pid.is_some_and(|program_pid| execution_result.res_usage.load_proc_stat(program_pid).is_ok());
 
-            // Fetch counters' values from the processes `stat` file
-            if execution_result.res_usage.load_proc_stat(child_pid).is_err() { continue }
+            // Forbidden actions, detected by the supervisor
+            if notify_supervisor.as_ref().is_some_and(|supervisor| supervisor.security_violation)
+            { kill_with_reason(child_pid, &mut execution_result, KILL_REASON_SECURITY); continue; }
 
             // Wall clock time usage limiting
             if exec_prog_limits.limit_real_time > 0 && execution_result.res_usage.real_time > exec_prog_limits.limit_real_time
             { kill_with_reason(child_pid, &mut execution_result, KILL_REASON_REALTIME); continue; }
 
             // Processor time usage imiting
-            if exec_prog_limits.limit_proc_time > 0 && execution_result.res_usage.proc_time > exec_prog_limits.limit_proc_time
+            if stat_loaded && exec_prog_limits.limit_proc_time > 0 && execution_result.res_usage.proc_time > exec_prog_limits.limit_proc_time
             { kill_with_reason(child_pid, &mut execution_result, KILL_REASON_PROCTIME); continue; }
 
             // Peak working set usage limiting
-            if exec_prog_limits.limit_proc_wset > 0 && execution_result.res_usage.proc_wset > exec_prog_limits.limit_proc_wset
+            if stat_loaded && exec_prog_limits.limit_proc_wset > 0 && execution_result.res_usage.proc_wset > exec_prog_limits.limit_proc_wset
             { kill_with_reason(child_pid, &mut execution_result, KILL_REASON_PROCWSET); continue; }
 
             fn kill_with_reason(child_pid: pid_t, execution_result: &mut ProcExecResult, kill_reason: c_int)
             {
-                unsafe { libc::kill(child_pid, libc::SIGKILL) };
+                sandboxing_features::kill_process_group(child_pid);
                 execution_result.is_killed   = true;
                 execution_result.kill_reason = kill_reason;
             }
@@ -145,13 +362,30 @@ fn execute_internal(
              * not yet used all of the allowed amout of system resources, so
              * now we need to continue our loop after a certain timeout.
              */
-            std::thread::sleep(loop_exec_timeout);
+            match notify_supervisor.as_mut() {
+                Some(notify_supervisor) => notify_supervisor.serve(loop_exec_timeout),
+                None => std::thread::sleep(loop_exec_timeout)
+            }
             continue;
         }
         /* ===== /@On child process [executing] ===== */
 
         /* ===== @On child process [state changed] ===== */
 
+        // Processes left by the program (for example, forked to the background) are killed before it is reaped
+        sandboxing_features::kill_process_group(child_pid);
+
+        // Use MaybeUninit to initialize variables used by `wait4` system call
+        let mut waitpid_status = MaybeUninit::<c_int>::uninit();
+        let mut waitpid_rusage = MaybeUninit::<libc::rusage>::uninit();
+
+        // Panic on `wait4` system call execution error
+        if unsafe { libc::wait4(child_pid, waitpid_status.as_mut_ptr(), 0, waitpid_rusage.as_mut_ptr()) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("wait4"); }
+
+        let waitpid_status = unsafe { waitpid_status.assume_init() };
+        let waitpid_rusage = unsafe { waitpid_rusage.assume_init() };
+
         // Gather process stats from `rusage` struct
         execution_result.res_usage.load_rusage(&waitpid_rusage);
 
@@ -173,8 +407,10 @@ fn execute_internal(
             {
                 // Handle `SIGSYS` like when child process tries to use forbidden system features.
                 // For example, `seccomp` kernel feature uses `SIGSYS` to kill processes that try
-                // to use system calls, forbidden by the current enforced policy.
+                // to use system calls, forbidden by the current enforced policy. The supervisor
+                // kills processes with `SIGKILL`, so we also check whether it detected anything.
                 if execution_result.exit_sign == libc::SIGSYS
+                    || notify_supervisor.as_ref().is_some_and(|supervisor| supervisor.security_violation)
                 { execution_result.kill_reason = KILL_REASON_SECURITY; }
 
                 // WALL CLOCK TIME LIMIT
@@ -199,7 +435,29 @@ fn execute_internal(
         /* ===== /@On child process [state changed] ===== */
     }
 
-    execution_result
+    // Processes of the killed group may outlive the child process, so they are waited for
+    execution_result.group_leftover = !sandboxing_features::wait_process_group(child_pid);
+
+    let setup_report = setup_channel.receive_report();
+    execution_result.unshare_created = setup_report.unshare_created;
+
+    if let Some((setup_failure, setup_errno)) = setup_report.failure {
+        execution_result.setup_failure = setup_failure;
+        execution_result.setup_errno = setup_errno;
+
+        // Child process exits before the program is restricted
+        execution_result.landlock_abi = 0;
+        execution_result.landlock_access = 0;
+    }
+
+    let mut scmp_profile : Option<SyscallProfile> = None;
+
+    if let Some(mut notify_supervisor) = notify_supervisor {
+        execution_result.scmp_denied = notify_supervisor.denied_count;
+        scmp_profile = notify_supervisor.profile.take();
+    }
+
+    (execution_result, scmp_profile)
 
     /* ===== /[PARENT] PROCESS CODE FRAGMENT ===== */
 }
@@ -207,19 +465,48 @@ fn execute_internal(
 fn exec_child_cmd(
     exec_prog_info   : &ExecProgInfo,
     exec_prog_io     : &ExecProgIO,
-    exec_prog_limits : &ExecProgLimits,
-    exec_prog_guard  : &ExecProgGuard,
-    exec_path_normal : &CStr,
+    exec_prog_policy : &ExecProgPolicy,
+    exec_as_ids      : Option<(uid_t, gid_t)>,
+    notify_channel   : Option<&NotifyChannel>,
+    setup_channel    : &SetupChannel,
     exec_argv_normal : Vec<CString>)
 {
+    let exec_path_normal = unsafe { CStr::from_ptr(exec_prog_info.program_path) };
+
+    // Child process leads a new session and process group, so all of its processes are killed at once
+    if unsafe { libc::setsid() } == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("setsid"); }
+
+    // Existing namespaces are joined before anything else, while the child process is still privileged
+    let setns_namespaces = exec_prog_policy.setns_namespaces;
+    sandboxing_features::join_namespaces(&exec_prog_policy.exec_prog_guard, setup_channel);
+
+    // User namespace is mapped to the user we run as, so it must be changed before
+    let unshare_namespaces = exec_prog_policy.exec_prog_guard.get_unshare_namespaces() & !setns_namespaces;
+    let unshare_user = unshare_namespaces & UNSHARE_NS_USER != 0;
+    if unshare_user { sandboxing_features::init_set_user_ids(exec_as_ids, setup_channel); }
+
     // Execute various resource limiting and sandboxing functions
-    sandboxing_features::unshare_resources(exec_prog_guard);
+    sandboxing_features::kill_on_parent_exit();
+    let unshare_created = sandboxing_features::unshare_resources(&exec_prog_policy.exec_prog_guard, setns_namespaces, setup_channel);
+    if exec_prog_policy.exec_prog_guard.net_loopback_up && unshare_created & UNSHARE_NS_NET != 0 { network::init_loopback(setup_channel); }
+
+    // Init process is forked even if the PID namespace was not created, so the parent process can find the program
+    let mount_layout = unshare_created & (UNSHARE_NS_MOUNT | UNSHARE_NS_PID) == UNSHARE_NS_MOUNT | UNSHARE_NS_PID;
+    if (unshare_namespaces | setns_namespaces) & UNSHARE_NS_PID != 0 { sandboxing_features::init_pid_namespace(exec_prog_info, &exec_prog_policy.exec_prog_guard, mount_layout, setup_channel); }
     sandboxing_features::set_work_dir(exec_prog_info);
+    privileges::init_lock_capabilities(&exec_prog_policy.exec_prog_guard, setup_channel);
+    if !unshare_user { sandboxing_features::init_set_user_ids(exec_as_ids, setup_channel); }
     sandboxing_features::kill_on_parent_exit();
-    sandboxing_features::init_set_user_id(exec_prog_info);
-    sandboxing_features::set_resource_limits(exec_prog_limits);
-    sandboxing_features::redirect_io_streams(exec_prog_io);
-    sandboxing_features::init_secure_computing(exec_prog_guard);
+    sandboxing_features::set_resource_limits(&exec_prog_policy.exec_prog_limits);
+    sandboxing_features::redirect_io_streams(exec_prog_io, setup_channel);
+    sandboxing_features::close_inherited_fds(exec_prog_io);
+    network::pass_unix_sockets(exec_prog_io, setup_channel);
+    sandboxing_features::map_caller_fds(exec_prog_io);
+    landlock::init_landlock(exec_prog_info, exec_prog_io, &exec_prog_policy.exec_prog_guard, exec_prog_policy.landlock_abi, setup_channel);
+    privileges::init_drop_privileges(&exec_prog_policy.exec_prog_guard, setup_channel);
+    sandboxing_features::reset_signals(&exec_prog_policy.exec_prog_guard);
+    sandboxing_features::init_secure_computing(&exec_prog_policy.exec_prog_guard, exec_prog_policy.scmp_filters.as_ref(), notify_channel);
 
     // Try to execute program using EXECV
     if nix::unistd::execv(exec_path_normal, exec_argv_normal.as_slice()).is_ok()
@@ -228,3 +515,18 @@ fn exec_child_cmd(
     // Exit with status code 100 on error
     unsafe { libc::exit(100 as c_int); }
 }
+
+/*
+ * Child process executes the program after changing its working directory,
+ * so a relative program path is resolved against it, not the current one.
+ */
+fn get_exec_path_absolute(exec_prog_info : &ExecProgInfo, exec_path_normal : &CStr) -> PathBuf
+{
+    let working_path = unsafe { CStr::from_ptr(exec_prog_info.working_path) }.to_str().unwrap();
+    let exec_path = Path::new(working_path).join(exec_path_normal.to_str().unwrap());
+
+    match std::env::current_dir() {
+        Ok(current_dir) => seccomp_notify::normalize_path(&current_dir.join(exec_path)),
+        Err(_) => exec_path
+    }
+}
@@ -0,0 +1,363 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::{CStr, CString};
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use libc::{c_ulong, MS_BIND, MS_NODEV, MS_NOEXEC, MS_NOSUID, MS_PRIVATE, MS_RDONLY, MS_REC, MS_REMOUNT};
+use crate::{ExecProgGuard, ExecProgInfo, SYS_EXEC_FAILED};
+use crate::constants::SETUP_FAILURE_MOUNT;
+use crate::setup_channel::SetupChannel;
+
+/*
+ * [INIT] Builds the filesystem of a new mount namespace. Without a new root, the host
+ * filesystem is kept and only a fresh `/proc`, minimal `/dev` and tmpfs instances are
+ * mounted. Otherwise, the root directory is mounted read-only (along with its sub-mounts),
+ * with read-only bind mounts, minimal `/dev`, tmpfs instances and a writable working
+ * directory on top of it, using
+ * the same paths as in the host system. Mount points must be present, unless they are
+ * inside of a tmpfs. All mounts (including tmpfs contents) are destroyed along with the
+ * namespace when the init process exits.
+ */
+
+pub fn init_mount_layout(exec_prog_info : &ExecProgInfo, exec_prog_guard : &ExecProgGuard, setup_channel : &SetupChannel)
+{
+    let mount = |source : Option<&Path>, target : &Path, fs_type : Option<&str>, flags : c_ulong, data : Option<&str>| {
+        if !mount_filesystem(source, target, fs_type, flags, data)
+        { setup_channel.report_failure(SETUP_FAILURE_MOUNT); }
+    };
+
+    // Mount events must not propagate to the host system
+    mount(None, Path::new("/"), None, MS_REC | MS_PRIVATE, None);
+
+    let mut mount_flags : c_ulong = 0;
+    if exec_prog_guard.mount_nosuid { mount_flags |= MS_NOSUID; }
+    if exec_prog_guard.mount_nodev { mount_flags |= MS_NODEV; }
+
+    // Files written by the program may be not executable
+    let write_flags = if exec_prog_guard.mount_noexec { mount_flags | MS_NOEXEC } else { mount_flags };
+
+    let work_path = PathBuf::from(unsafe { CStr::from_ptr(exec_prog_info.working_path) }.to_str().unwrap());
+    let work_path = std::fs::canonicalize(&work_path).unwrap_or(work_path);
+
+    let tmpfs_paths = exec_prog_guard.get_mount_tmpfs_vec();
+    let tmpfs_data = get_tmpfs_data(exec_prog_guard);
+    let in_tmpfs = |path : &Path| tmpfs_paths.iter().any(|tmpfs_path| path.starts_with(tmpfs_path));
+
+    // Device nodes of the host are opened first, so they are still reachable under a new `/dev`
+    let dev_nodes = exec_prog_guard.mount_dev.then(|| open_dev_nodes()
+        .unwrap_or_else(|| setup_channel.report_failure(SETUP_FAILURE_MOUNT)));
+    let mount_dev = |dev_path : &Path| if let Some(dev_nodes) = dev_nodes.as_ref() {
+        if !mount_dev_filesystem(dev_path, dev_nodes)
+        { setup_channel.report_failure(SETUP_FAILURE_MOUNT); }
+    };
+
+    let root_path = match exec_prog_guard.get_mount_root_path() {
+        Some(root_path) => root_path,
+        None => {
+            // Working directory would be hidden by a tmpfs, so we bind it back using a descriptor
+            let work_dir = in_tmpfs(&work_path).then(|| File::open(&work_path)
+                .unwrap_or_else(|_| setup_channel.report_failure(SETUP_FAILURE_MOUNT)));
+
+            mount_dev(Path::new("/dev"));
+
+            for tmpfs_path in tmpfs_paths.iter()
+            { mount(Some(Path::new("tmpfs")), tmpfs_path, Some("tmpfs"), write_flags, Some(&tmpfs_data)); }
+
+            if let Some(work_dir) = work_dir
+            {
+                let _ = std::fs::create_dir_all(&work_path);
+                let work_dir_path = PathBuf::from(format!("/proc/self/fd/{}", work_dir.as_raw_fd()));
+                mount(Some(&work_dir_path), &work_path, None, MS_BIND | MS_REC, None);
+            }
+
+            return mount(Some(Path::new("proc")), Path::new("/proc"), Some("proc"), MS_NOSUID | MS_NODEV | MS_NOEXEC, None);
+        }
+    };
+
+    let remount_read_only = |target : &Path| {
+        if !remount_read_only(target, mount_flags)
+        { setup_channel.report_failure(SETUP_FAILURE_MOUNT); }
+    };
+
+    // Root directory must be a mount point to pivot into it, and writable mounts are added later
+    mount(Some(&root_path), &root_path, None, MS_BIND | MS_REC, None);
+    remount_read_only(&root_path);
+
+    for bind_path in exec_prog_guard.get_mount_bind_ro_vec()
+    {
+        let target_path = get_path_in_root(&root_path, &bind_path);
+        mount(Some(&bind_path), &target_path, None, MS_BIND | MS_REC, None);
+        remount_read_only(&target_path);
+    }
+
+    mount_dev(&root_path.join("dev"));
+
+    for tmpfs_path in tmpfs_paths.iter()
+    { mount(Some(Path::new("tmpfs")), &get_path_in_root(&root_path, tmpfs_path), Some("tmpfs"), write_flags, Some(&tmpfs_data)); }
+
+    let target_path = get_path_in_root(&root_path, &work_path);
+    if in_tmpfs(&work_path) { let _ = std::fs::create_dir_all(&target_path); }
+
+    mount(Some(&work_path), &target_path, None, MS_BIND | MS_REC, None);
+    mount(None, &target_path, None, MS_BIND | MS_REMOUNT | write_flags | get_locked_flags(&target_path), None);
+
+    mount(Some(Path::new("proc")), &root_path.join("proc"), Some("proc"), MS_NOSUID | MS_NODEV | MS_NOEXEC, None);
+
+    /*
+     * Stack the old root on top of the new one and detach it, so there is no
+     * directory to put the old root into, and no way to reach it afterwards.
+     */
+    let root_path = CString::new(root_path.to_str().unwrap()).unwrap();
+    let (dot_path, slash_path) = (CString::new(".").unwrap(), CString::new("/").unwrap());
+
+    unsafe {
+        if libc::chdir(root_path.as_ptr()) == SYS_EXEC_FAILED
+            || libc::syscall(libc::SYS_pivot_root, dot_path.as_ptr(), dot_path.as_ptr()) == SYS_EXEC_FAILED as i64
+            || libc::umount2(dot_path.as_ptr(), libc::MNT_DETACH) == SYS_EXEC_FAILED
+            || libc::chdir(slash_path.as_ptr()) == SYS_EXEC_FAILED
+        { setup_channel.report_failure(SETUP_FAILURE_MOUNT); }
+    }
+}
+
+/*
+ * Minimal `/dev` is a read-only tmpfs with device nodes of the host bound into it. Even
+ * if the program runs in a user namespace, these nodes keep working, because they are
+ * still on the filesystem of the host. Tmpfs may be mounted at its `shm` directory.
+ */
+
+const DEV_NODES : [&str; 6] = ["null", "zero", "full", "random", "urandom", "tty"];
+const DEV_LINKS : [(&str, &str); 4] = [
+    ("fd", "/proc/self/fd"), ("stdin", "/proc/self/fd/0"),
+    ("stdout", "/proc/self/fd/1"), ("stderr", "/proc/self/fd/2")
+];
+
+fn open_dev_nodes() -> Option<Vec<File>>
+{
+    DEV_NODES.iter()
+        .map(|dev_node| OpenOptions::new().read(true).custom_flags(libc::O_PATH).open(Path::new("/dev").join(dev_node)).ok())
+        .collect()
+}
+
+fn mount_dev_filesystem(dev_path : &Path, dev_nodes : &[File]) -> bool
+{
+    let dev_flags = MS_NOSUID | MS_NODEV | MS_NOEXEC;
+    if !mount_filesystem(Some(Path::new("tmpfs")), dev_path, Some("tmpfs"), dev_flags, Some("mode=755,size=65536"))
+    { return false; }
+
+    for (dev_node, dev_file) in DEV_NODES.iter().zip(dev_nodes)
+    {
+        let node_path = dev_path.join(dev_node);
+        let source_path = PathBuf::from(format!("/proc/self/fd/{}", dev_file.as_raw_fd()));
+
+        if File::create(&node_path).is_err() || !mount_filesystem(Some(&source_path), &node_path, None, MS_BIND, None)
+        { return false; }
+    }
+
+    for (link_name, link_target) in DEV_LINKS
+    {
+        if std::os::unix::fs::symlink(link_target, dev_path.join(link_name)).is_err()
+        { return false; }
+    }
+
+    std::fs::create_dir(dev_path.join("shm")).is_ok()
+        && mount_filesystem(None, dev_path, None, MS_BIND | MS_REMOUNT | MS_RDONLY | dev_flags, None)
+}
+
+/* @Mount options of tmpfs instances, which are writable for everyone, like `/tmp` */
+fn get_tmpfs_data(exec_prog_guard : &ExecProgGuard) -> String
+{
+    let mut tmpfs_data = String::from("mode=1777");
+
+    // Zero values mean no limits for tmpfs, so kernel defaults are kept instead
+    if exec_prog_guard.mount_tmpfs_size > 0 { tmpfs_data += &format!(",size={}", exec_prog_guard.mount_tmpfs_size); }
+    if exec_prog_guard.mount_tmpfs_inodes > 0 { tmpfs_data += &format!(",nr_inodes={}", exec_prog_guard.mount_tmpfs_inodes); }
+
+    tmpfs_data
+}
+
+fn get_path_in_root(root_path : &Path, path : &Path) -> PathBuf
+{
+    root_path.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+/*
+ * Mounts inherited from a more privileged mount namespace have some flags locked,
+ * so a remount must keep them, otherwise it fails even if it adds new restrictions.
+ */
+fn get_locked_flags(path : &Path) -> c_ulong
+{
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } == SYS_EXEC_FAILED { return 0; }
+    let stat_flags = unsafe { stat.assume_init() }.f_flag;
+
+    [(libc::ST_NOSUID, MS_NOSUID), (libc::ST_NODEV, MS_NODEV), (libc::ST_NOEXEC, MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME), (libc::ST_NODIRATIME, libc::MS_NODIRATIME), (libc::ST_RELATIME, libc::MS_RELATIME)]
+        .iter()
+        .filter(|(stat_flag, _)| stat_flags & stat_flag != 0)
+        .fold(0, |flags, (_, mount_flag)| flags | mount_flag)
+}
+
+/*
+ * Bind mounts with `MS_REC` copy sub-mounts as they are, while a remount only changes
+ * the top one, so all of them are made read-only at once with `mount_setattr`. Kernels
+ * before 5.12 don't support it, so each mount found in `/proc/self/mountinfo` under the
+ * path is remounted instead. Other flags are set on the top mount only, so device nodes
+ * of sub-mounts (such as `/dev` of the host) keep working.
+ */
+
+#[repr(C)]
+struct MountAttr // Not yet exported by the `libc` crate version we use
+{
+    attr_set    : u64,
+    attr_clr    : u64,
+    propagation : u64,
+    userns_fd   : u64
+}
+
+const MOUNT_ATTR_RDONLY : u64 = 0x01;
+
+fn remount_read_only(path : &Path, mount_flags : c_ulong) -> bool
+{
+    let mount_attr = MountAttr { attr_set: MOUNT_ATTR_RDONLY, attr_clr: 0, propagation: 0, userns_fd: 0 };
+    let target = CString::new(path.to_str().unwrap()).unwrap();
+
+    let result = unsafe {
+        libc::syscall(libc::SYS_mount_setattr, libc::AT_FDCWD, target.as_ptr(), libc::AT_RECURSIVE,
+                      &mount_attr as *const MountAttr, std::mem::size_of::<MountAttr>())
+    };
+
+    let is_read_only = if result != SYS_EXEC_FAILED as i64 { true }
+        else { nix::errno::errno() == libc::ENOSYS && remount_each_read_only(path) };
+
+    is_read_only && mount_filesystem(None, path, None, MS_BIND | MS_REMOUNT | MS_RDONLY | mount_flags | get_locked_flags(path), None)
+}
+
+fn remount_each_read_only(path : &Path) -> bool
+{
+    let Some(mount_paths) = get_mount_paths() else { return false };
+
+    // Current flags are kept, as a remount clears the ones it doesn't set
+    mount_paths.iter()
+        .filter(|mount_path| mount_path.starts_with(path))
+        .all(|mount_path| mount_filesystem(None, mount_path, None, MS_BIND | MS_REMOUNT | MS_RDONLY | get_locked_flags(mount_path), None))
+}
+
+/* Mount points of `/proc/self/mountinfo`, where spaces and other special characters are octal escapes */
+fn get_mount_paths() -> Option<Vec<PathBuf>>
+{
+    let mount_info = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+
+    mount_info.lines()
+        .map(|mount_line| mount_line.split(' ').nth(4).map(|mount_path| PathBuf::from(unescape_mount_path(mount_path))))
+        .collect()
+}
+
+fn unescape_mount_path(mount_path : &str) -> String
+{
+    let mut path_bytes = Vec::with_capacity(mount_path.len());
+    let mut input_bytes = mount_path.as_bytes();
+
+    while let Some((&byte, rest_bytes)) = input_bytes.split_first()
+    {
+        let escaped = (byte == b'\\').then(|| rest_bytes.get(..3)).flatten()
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+
+        match escaped {
+            Some(escaped) => { path_bytes.push(escaped); input_bytes = &rest_bytes[3..]; }
+            None => { path_bytes.push(byte); input_bytes = rest_bytes; }
+        }
+    }
+
+    String::from_utf8_lossy(&path_bytes).into_owned()
+}
+
+fn mount_filesystem(source : Option<&Path>, target : &Path, fs_type : Option<&str>, flags : c_ulong, data : Option<&str>) -> bool
+{
+    let source  = source.map(|source| CString::new(source.to_str().unwrap()).unwrap());
+    let target  = CString::new(target.to_str().unwrap()).unwrap();
+    let fs_type = fs_type.map(|fs_type| CString::new(fs_type).unwrap());
+    let data    = data.map(|data| CString::new(data).unwrap());
+
+    unsafe {
+        libc::mount(source.as_ref().map_or(std::ptr::null(), |source| source.as_ptr()), target.as_ptr(),
+                    fs_type.as_ref().map_or(std::ptr::null(), |fs_type| fs_type.as_ptr()), flags,
+                    data.as_ref().map_or(std::ptr::null(), |data| data.as_ptr() as *const libc::c_void)) != SYS_EXEC_FAILED
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::{Path, PathBuf};
+    use libc::MS_NOSUID;
+    use super::{mount_filesystem, remount_each_read_only, remount_read_only, unescape_mount_path};
+
+    /*
+     * Mounts a tmpfs with another one nested inside of it in a new mount namespace of a child
+     * process, makes the outer one read-only and checks the nested one can't be written.
+     */
+    fn check_nested_read_only(name : &str, remount : &dyn Fn(&Path) -> bool)
+    {
+        // Mount namespaces can't be created without privileges
+        if unsafe { libc::geteuid() } != 0 { return; }
+
+        let outer_path = std::env::temp_dir().join(format!("limtrac-{}-{}", name, std::process::id()));
+        let nested_path = outer_path.join("nested");
+        std::fs::create_dir_all(&outer_path).unwrap();
+
+        let child_pid = unsafe { libc::fork() };
+        if child_pid == 0
+        {
+            let is_read_only = unsafe { libc::unshare(libc::CLONE_NEWNS) } == 0
+                && mount_filesystem(None, Path::new("/"), None, libc::MS_REC | libc::MS_PRIVATE, None)
+                && mount_filesystem(Some(Path::new("tmpfs")), &outer_path, Some("tmpfs"), 0, None)
+                && std::fs::create_dir(&nested_path).is_ok()
+                && mount_filesystem(Some(Path::new("tmpfs")), &nested_path, Some("tmpfs"), 0, None)
+                && remount(&outer_path)
+                && std::fs::write(nested_path.join("file"), "")
+                    .is_err_and(|error| error.raw_os_error() == Some(libc::EROFS));
+
+            unsafe { libc::_exit(if is_read_only { 0 } else { 1 }) };
+        }
+
+        let mut status = 0;
+        unsafe { libc::waitpid(child_pid, &mut status, 0) };
+        std::fs::remove_dir_all(&outer_path).unwrap();
+
+        assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+    }
+
+    #[test]
+    fn nested_mounts_are_read_only()
+    {
+        check_nested_read_only("setattr", &|path| remount_read_only(path, MS_NOSUID));
+        check_nested_read_only("remount", &remount_each_read_only);
+    }
+
+    #[test]
+    fn unescape_mount_path_decodes_octal()
+    {
+        assert_eq!(unescape_mount_path("/tmp/a\\040b\\134c"), "/tmp/a b\\c");
+        assert_eq!(PathBuf::from(unescape_mount_path("/tmp/a\\04")), PathBuf::from("/tmp/a\\04"));
+    }
+}
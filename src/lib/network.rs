@@ -0,0 +1,122 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::mem::size_of;
+use libc::c_int;
+use crate::{ExecProgIO, SYS_EXEC_FAILED};
+use crate::constants::SETUP_FAILURE_NETWORK;
+use crate::setup_channel::SetupChannel;
+
+/*
+ * Kernel ABI definitions of link messages, see `linux/rtnetlink.h`. They
+ * are not yet exported by the `libc` crate version we use.
+ */
+
+#[repr(C)]
+struct IfInfoMsg
+{
+    ifi_family : u8,
+    ifi_pad    : u8,
+    ifi_type   : u16,
+    ifi_index  : c_int,
+    ifi_flags  : u32,
+    ifi_change : u32
+}
+
+#[repr(C)]
+struct LinkRequest
+{
+    header : libc::nlmsghdr,
+    info   : IfInfoMsg
+}
+
+// Loopback device is always the first one in a network namespace
+const LOOPBACK_IFINDEX : c_int = 1;
+
+/*
+ * [CHILD] New network namespace contains only the loopback device, which is down, so
+ * we bring it up using a `RTM_NEWLINK` request over `NETLINK_ROUTE` socket. It requires
+ * CAP_NET_ADMIN in the namespace, so it is done before credentials are changed.
+ */
+pub fn init_loopback(setup_channel : &SetupChannel)
+{
+    let netlink_fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_ROUTE) };
+    if netlink_fd == SYS_EXEC_FAILED { setup_channel.report_failure(SETUP_FAILURE_NETWORK); }
+
+    let link_request = LinkRequest {
+        header: libc::nlmsghdr {
+            nlmsg_len: size_of::<LinkRequest>() as u32,
+            nlmsg_type: libc::RTM_NEWLINK,
+            nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16,
+            nlmsg_seq: 1,
+            nlmsg_pid: 0
+        },
+        info: IfInfoMsg {
+            ifi_family: libc::AF_UNSPEC as u8,
+            ifi_pad: 0,
+            ifi_type: 0,
+            ifi_index: LOOPBACK_IFINDEX,
+            ifi_flags: libc::IFF_UP as u32,
+            ifi_change: libc::IFF_UP as u32
+        }
+    };
+
+    // Unbound netlink sockets send messages to the kernel
+    let request_size = unsafe { libc::send(netlink_fd, &link_request as *const LinkRequest as *const libc::c_void, size_of::<LinkRequest>(), 0) };
+    if request_size != size_of::<LinkRequest>() as isize { setup_channel.report_failure(SETUP_FAILURE_NETWORK); }
+
+    // Kernel acknowledges the request with an error message, which contains zero on success
+    let mut response = [0u32; 64];
+    let response_size = unsafe { libc::recv(netlink_fd, response.as_mut_ptr() as *mut libc::c_void, size_of_val(&response), 0) };
+
+    let response_size_min = size_of::<libc::nlmsghdr>() + size_of::<libc::nlmsgerr>();
+    if response_size < response_size_min as isize { setup_channel.report_failure(SETUP_FAILURE_NETWORK); }
+
+    let response_header = unsafe { &*(response.as_ptr() as *const libc::nlmsghdr) };
+    let response_error = unsafe { &*(response.as_ptr().add(size_of::<libc::nlmsghdr>() / size_of::<u32>()) as *const libc::nlmsgerr) };
+
+    if response_header.nlmsg_type != libc::NLMSG_ERROR as u16 || response_error.error != 0
+    {
+        unsafe { *libc::__errno_location() = -response_error.error };
+        setup_channel.report_failure(SETUP_FAILURE_NETWORK);
+    }
+
+    unsafe { libc::close(netlink_fd) };
+}
+
+/* [CHILD] Unix sockets, connected by the caller, are inherited by the program at the same numbers */
+pub fn pass_unix_sockets(exec_prog_io : &ExecProgIO, setup_channel : &SetupChannel)
+{
+    for socket_fd in exec_prog_io.get_unix_socket_vec()
+    {
+        if unsafe { libc::fcntl(socket_fd, libc::F_SETFD, 0) } == SYS_EXEC_FAILED
+        { setup_channel.report_failure(SETUP_FAILURE_NETWORK); }
+    }
+}
+
+/* Checks whether a file descriptor is open and refers to a Unix socket */
+pub fn is_unix_socket(socket_fd : c_int) -> bool
+{
+    let mut socket_domain : c_int = 0;
+    let mut option_size = size_of::<c_int>() as libc::socklen_t;
+
+    let result = unsafe { libc::getsockopt(socket_fd, libc::SOL_SOCKET, libc::SO_DOMAIN,
+                                           &mut socket_domain as *mut c_int as *mut libc::c_void, &mut option_size) };
+
+    result != SYS_EXEC_FAILED && socket_domain == libc::AF_UNIX
+}
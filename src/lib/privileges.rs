@@ -0,0 +1,118 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::c_int;
+use crate::{ExecProgGuard, SYS_EXEC_FAILED};
+use crate::constants::SETUP_FAILURE_PRIVILEGES;
+use crate::setup_channel::SetupChannel;
+
+/*
+ * Kernel ABI definitions of capabilities and secure bits, see `linux/capability.h`
+ * and `linux/securebits.h`. They are not yet exported by the `libc` crate version we use.
+ */
+
+const LINUX_CAPABILITY_VERSION_3 : u32 = 0x20080522;
+const CAP_SETPCAP : u32 = 8;
+
+const SECBIT_NOROOT : u64 = 1 << 0;
+const SECBIT_NOROOT_LOCKED : u64 = 1 << 1;
+const SECBIT_NO_SETUID_FIXUP_LOCKED : u64 = 1 << 3;
+const SECBIT_KEEP_CAPS_LOCKED : u64 = 1 << 5;
+const SECBIT_NO_CAP_AMBIENT_RAISE : u64 = 1 << 6;
+const SECBIT_NO_CAP_AMBIENT_RAISE_LOCKED : u64 = 1 << 7;
+
+#[repr(C)]
+struct CapUserHeader
+{
+    version : u32,
+    pid     : c_int
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapUserData
+{
+    effective   : u32,
+    permitted   : u32,
+    inheritable : u32
+}
+
+/*
+ * [CHILD] Drops all capabilities from the bounding set, so they can't be gained by executing
+ * set-user-ID or file capabilities binaries, and locks secure bits, so root user gains no
+ * capabilities on exec. It requires CAP_SETPCAP, so it is done before credentials are changed.
+ * Unprivileged processes have no capabilities to lose, so nothing is done for them.
+ */
+pub fn init_lock_capabilities(exec_prog_guard : &ExecProgGuard, setup_channel : &SetupChannel)
+{
+    if !exec_prog_guard.priv_drop_caps || !has_capability(CAP_SETPCAP) { return; }
+
+    // Capabilities are dropped until the kernel reports an unknown one
+    let mut capability = 0;
+    while unsafe { libc::prctl(libc::PR_CAPBSET_READ, capability, 0, 0, 0) } != SYS_EXEC_FAILED
+    {
+        if unsafe { libc::prctl(libc::PR_CAPBSET_DROP, capability, 0, 0, 0) } == SYS_EXEC_FAILED
+        { setup_channel.report_failure(SETUP_FAILURE_PRIVILEGES); }
+        capability += 1;
+    }
+
+    // User ID changes still clear capabilities, as the setuid fixup is locked enabled
+    let secure_bits = SECBIT_NOROOT | SECBIT_NOROOT_LOCKED | SECBIT_NO_SETUID_FIXUP_LOCKED
+        | SECBIT_KEEP_CAPS_LOCKED | SECBIT_NO_CAP_AMBIENT_RAISE | SECBIT_NO_CAP_AMBIENT_RAISE_LOCKED;
+
+    if unsafe { libc::prctl(libc::PR_SET_SECUREBITS, secure_bits, 0, 0, 0) } == SYS_EXEC_FAILED
+    { setup_channel.report_failure(SETUP_FAILURE_PRIVILEGES); }
+}
+
+/*
+ * [CHILD] Drops effective, permitted, inheritable and ambient capabilities, forbids gaining
+ * new privileges on exec, and makes the process not dumpable, so other processes of the
+ * same user can't attach to it or read its memory (which is a copy of the caller's memory)
+ * before the program starts. Note that exec makes most programs dumpable again.
+ */
+pub fn init_drop_privileges(exec_prog_guard : &ExecProgGuard, setup_channel : &SetupChannel)
+{
+    if exec_prog_guard.priv_drop_caps
+    {
+        let cap_header = CapUserHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+        let cap_data = [CapUserData::default(); 2];
+
+        unsafe {
+            if libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_CLEAR_ALL, 0, 0, 0) == SYS_EXEC_FAILED
+                || libc::syscall(libc::SYS_capset, &cap_header, cap_data.as_ptr()) == SYS_EXEC_FAILED as i64
+            { setup_channel.report_failure(SETUP_FAILURE_PRIVILEGES); }
+        }
+    }
+
+    if exec_prog_guard.priv_no_new_privs && unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } == SYS_EXEC_FAILED
+    { setup_channel.report_failure(SETUP_FAILURE_PRIVILEGES); }
+
+    if exec_prog_guard.priv_not_dumpable && unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } == SYS_EXEC_FAILED
+    { setup_channel.report_failure(SETUP_FAILURE_PRIVILEGES); }
+}
+
+fn has_capability(capability : u32) -> bool
+{
+    let mut cap_header = CapUserHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+    let mut cap_data = [CapUserData::default(); 2];
+
+    if unsafe { libc::syscall(libc::SYS_capget, &mut cap_header, cap_data.as_mut_ptr()) } == SYS_EXEC_FAILED as i64
+    { return false; }
+
+    cap_data[(capability / 32) as usize].effective & (1 << (capability % 32)) != 0
+}
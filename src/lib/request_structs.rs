@@ -17,8 +17,12 @@
  */
 
 use std::ffi::{CStr, CString};
-use std::path::Path;
-use libc::{c_char, c_ulong, c_ulonglong};
+use std::path::{Path, PathBuf};
+use libc::{c_char, c_int, c_uint, c_ulong, c_ulonglong, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
+use crate::constants::{IO_FILE_MODE_DEFAULT, IO_MODE_APPEND, IO_MODE_AUTO, IO_MODE_FILE, IO_MODE_INHERIT, IO_MODE_NULL, IO_MODE_PIPE};
+use crate::constants::{IO_OPEN_APPEND, IO_OPEN_EXCLUSIVE, IO_OPEN_TRUNCATE};
+use crate::constants::{UNSHARE_NS_ALL, UNSHARE_NS_COMMON, UNSHARE_NS_MOUNT, UNSHARE_NS_NET, UNSHARE_NS_PID};
+use crate::SYS_EXEC_FAILED;
 
 #[repr(C)]
 pub struct ExecProgInfo
@@ -40,8 +44,8 @@ impl ExecProgInfo {
 
     fn check_paths(&self) -> bool
     {
-        return Path::new(unsafe { CStr::from_ptr(self.program_path) }.to_str().unwrap()).is_file()
-            && Path::new(unsafe { CStr::from_ptr(self.working_path) }.to_str().unwrap()).is_dir();
+        Path::new(unsafe { CStr::from_ptr(self.program_path) }.to_str().unwrap()).is_file()
+            && Path::new(unsafe { CStr::from_ptr(self.working_path) }.to_str().unwrap()).is_dir()
     }
 
     pub fn verify(&self) -> bool
@@ -76,6 +80,12 @@ impl ExecProgInfo {
     }
 }
 
+/* Checks whether a file descriptor is open in the current process */
+fn is_fd_open(fd : c_int) -> bool
+{
+    unsafe { libc::fcntl(fd, libc::F_GETFD) != SYS_EXEC_FAILED }
+}
+
 #[repr(C)]
 pub struct ExecProgIO
 {
@@ -83,7 +93,29 @@ pub struct ExecProgIO
     pub io_path_stdin : *const c_char,
     pub io_path_stdout : *const c_char,
     pub io_path_stderr : *const c_char,
-    pub io_dup_err_out : bool
+    pub io_dup_err_out : bool,
+    pub io_unix_sockets : *const c_char, // Colon-separated list of connected Unix sockets to pass
+    pub io_keep_fds : *const c_char,     // Colon-separated list of other descriptors to pass (others are closed)
+    pub io_fd_stdin : c_int,             // Caller-owned descriptor used as stdin (-1 if not used)
+    pub io_fd_stdout : c_int,            // Caller-owned descriptor used as stdout (-1 if not used)
+    pub io_fd_stderr : c_int,            // Caller-owned descriptor used as stderr (-1 if not used)
+    pub io_fd_map : *const c_char,       // Colon-separated list of `host=child` descriptor pairs
+    pub io_mode_stdin : c_int,           // Redirection mode of stdin, see `IO_MODE_*`
+    pub io_mode_stdout : c_int,          // Redirection mode of stdout, see `IO_MODE_*`
+    pub io_mode_stderr : c_int,          // Redirection mode of stderr, see `IO_MODE_*`
+    pub io_open_policy : c_int,          // Policy of opening output files, see `IO_OPEN_*`
+    pub io_file_mode : c_uint            // Permissions of created output files (0 for default)
+}
+
+/* Redirection mode of a standard stream, resolved from `IO_MODE_*` and given paths or descriptors */
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IoStreamMode
+{
+    File,    // File at the given path, created if it doesn't exist
+    Null,    // Redirected to `/dev/null`
+    Inherit, // Kept as inherited from the caller
+    Pipe,    // Caller-owned descriptor (a pipe, socket, memfd or file)
+    Append   // File at the given path, written at its end
 }
 
 impl ExecProgIO
@@ -97,47 +129,228 @@ impl ExecProgIO
 
     pub fn verify(&self) -> bool
     {
-        if !self.io_redirected { return true; }
+        // Sockets are passed even if I/O streams are not redirected
+        if self.io_unix_sockets.is_null() { return false; }
+
+        let unix_sockets = unsafe { CStr::from_ptr(self.io_unix_sockets) }.to_str().unwrap();
+        if !unix_sockets.split(':').filter(|socket_fd| !socket_fd.is_empty()).all(|socket_fd| socket_fd.parse::<c_int>()
+            .is_ok_and(|socket_fd| socket_fd > STDERR_FILENO && crate::network::is_unix_socket(socket_fd)))
+        { return false; }
+
+        // Descriptors to keep must be open
+        if self.io_keep_fds.is_null() { return false; }
+
+        let keep_fds = unsafe { CStr::from_ptr(self.io_keep_fds) }.to_str().unwrap();
+        if !keep_fds.split(':').filter(|keep_fd| !keep_fd.is_empty()).all(|keep_fd| keep_fd.parse::<c_int>()
+            .is_ok_and(|keep_fd| keep_fd > STDERR_FILENO && is_fd_open(keep_fd)))
+        { return false; }
+
+        /*
+         * Mapped descriptors must be open in the caller, and each target
+         * number must be used only once and not clash with passed ones.
+         */
+        if self.io_fd_map.is_null() { return false; }
+
+        let fd_map = unsafe { CStr::from_ptr(self.io_fd_map) }.to_str().unwrap();
+        let fd_map_entries = fd_map.split(':').filter(|fd_pair| !fd_pair.is_empty()).count();
+        let fd_table_vec = self.get_fd_table_vec();
+
+        if fd_table_vec.len() != fd_map_entries { return false; }
+
+        let mut child_fds : Vec<c_int> = Vec::new();
+        for (host_fd, child_fd) in fd_table_vec
+        {
+            if host_fd <= STDERR_FILENO || child_fd <= STDERR_FILENO || !is_fd_open(host_fd) { return false; }
+            if child_fds.contains(&child_fd) { return false; }
+            child_fds.push(child_fd);
+        }
+
+        if self.get_keep_fd_vec().iter().chain(self.get_unix_socket_vec().iter())
+            .any(|passed_fd| child_fds.contains(passed_fd))
+        { return false; }
+
+        // Modes are used only if I/O streams are redirected
+        let stream_modes = [self.io_mode_stdin, self.io_mode_stdout, self.io_mode_stderr];
+
+        if !self.io_redirected { return stream_modes.iter().all(|&stream_mode| stream_mode == IO_MODE_AUTO); }
         if !self.check_ptrs() { return false; }
 
         /*
-         * Try to convert raw C strings into CStr, so
-         * we can operate with them in a safe way.
+         * Each stream must be given exactly what
+         * its mode needs: a path for files, an open
+         * caller-owned descriptor for pipes, and
+         * nothing for null and inherited streams.
          */
-        let fpath_stdin = unsafe { CStr::from_ptr(self.io_path_stdin) };
-        let fpath_stdout = unsafe { CStr::from_ptr(self.io_path_stdout) };
-        let fpath_stderr = unsafe { CStr::from_ptr(self.io_path_stderr) };
+        for stream_fd in [STDIN_FILENO, STDOUT_FILENO, STDERR_FILENO]
+        {
+            let stream_path = self.get_stream_path(stream_fd).to_bytes();
+            let stream_desc = self.get_stream_desc(stream_fd);
+
+            let stream_valid = match self.get_stream_mode(stream_fd)
+            {
+                Some(IoStreamMode::File) => !stream_path.is_empty() && stream_desc == -1,
+                Some(IoStreamMode::Append) => !stream_path.is_empty() && stream_desc == -1 && stream_fd != STDIN_FILENO,
+                Some(IoStreamMode::Pipe) => stream_path.is_empty() && stream_desc > STDERR_FILENO && is_fd_open(stream_desc),
+                Some(IoStreamMode::Null | IoStreamMode::Inherit) => stream_path.is_empty() && stream_desc == -1,
+                None => false
+            };
+
+            if !stream_valid { return false; }
+        }
 
         /*
-         * All paths cannot be empty if we
+         * All streams cannot be empty if we
          * see that IO redirection feature
-         * is enabled by the LIMTRAC caller.
+         * is enabled by the LIMTRAC caller
+         * without choosing modes explicitly.
          */
-        if fpath_stdin.to_bytes().is_empty()
-            && fpath_stdout.to_bytes().is_empty()
-            && fpath_stderr.to_bytes().is_empty()
+        if stream_modes.iter().all(|&stream_mode| stream_mode == IO_MODE_AUTO)
+            && [STDIN_FILENO, STDOUT_FILENO, STDERR_FILENO].iter().all(|&stream_fd| self.get_stream_mode(stream_fd) == Some(IoStreamMode::Null))
         { return false; }
 
         /*
-         * Stderr cannot be passed to file and to
-         * stdout simulateously. After that, check
-         * whether stdout is redirected to a file.
+         * Stderr cannot be passed somewhere and
+         * to stdout simulateously. After that,
+         * check whether stdout goes anywhere.
          */
         if self.io_dup_err_out
-            && (!fpath_stderr.to_bytes().is_empty()
-            || fpath_stdout.to_bytes().is_empty())
+            && (self.io_mode_stderr != IO_MODE_AUTO
+            || self.get_stream_mode(STDERR_FILENO) != Some(IoStreamMode::Null)
+            || self.get_stream_mode(STDOUT_FILENO) == Some(IoStreamMode::Null))
         { return false; }
 
+        // Output files are created with permission bits only
+        if !matches!(self.io_open_policy, IO_OPEN_TRUNCATE | IO_OPEN_APPEND | IO_OPEN_EXCLUSIVE) { return false; }
+        if self.io_file_mode & !0o777 != 0 { return false; }
+
         // If STDIN redirection is enabled, input file must be present
-        if !fpath_stdin.to_bytes().is_empty() && !Path::new(fpath_stdin.to_str().unwrap()).is_file()
+        let fpath_stdin = self.get_stream_path(STDIN_FILENO);
+        if self.get_stream_mode(STDIN_FILENO) == Some(IoStreamMode::File) && !Path::new(fpath_stdin.to_str().unwrap()).is_file()
         { return false; }
 
         // All checks passed
         true
     }
+
+    pub fn get_keep_fd_vec(&self) -> Vec<c_int>
+    {
+        let keep_fds = unsafe { CStr::from_ptr(self.io_keep_fds) }.to_str().unwrap();
+
+        keep_fds.split(':')
+            .filter_map(|keep_fd| keep_fd.parse::<c_int>().ok())
+            .collect()
+    }
+
+    /*
+     * Resolves the redirection mode of a standard stream (by its number), or
+     * returns `None` if the mode is unknown. Auto mode prefers a descriptor.
+     */
+    pub fn get_stream_mode(&self, stream_fd : c_int) -> Option<IoStreamMode>
+    {
+        if !self.io_redirected { return Some(IoStreamMode::Inherit); }
+
+        let stream_mode = match stream_fd
+        {
+            STDIN_FILENO => self.io_mode_stdin,
+            STDOUT_FILENO => self.io_mode_stdout,
+            _ => self.io_mode_stderr
+        };
+
+        match stream_mode
+        {
+            IO_MODE_AUTO if self.get_stream_desc(stream_fd) != -1 => Some(IoStreamMode::Pipe),
+            IO_MODE_AUTO if !self.get_stream_path(stream_fd).to_bytes().is_empty() => Some(IoStreamMode::File),
+            IO_MODE_AUTO | IO_MODE_NULL => Some(IoStreamMode::Null),
+            IO_MODE_FILE => Some(IoStreamMode::File),
+            IO_MODE_INHERIT => Some(IoStreamMode::Inherit),
+            IO_MODE_PIPE => Some(IoStreamMode::Pipe),
+            IO_MODE_APPEND => Some(IoStreamMode::Append),
+            _ => None
+        }
+    }
+
+    /*
+     * Returns `open` flags for output files. Files are truncated, unless appended to,
+     * and may be required to be new. Symbolic links are refused by `open_output_file`.
+     */
+    pub fn get_output_flags(&self, append_mode : bool) -> c_int
+    {
+        let write_flag = if append_mode || self.io_open_policy == IO_OPEN_APPEND { libc::O_APPEND } else { libc::O_TRUNC };
+        let exclusive_flag = if self.io_open_policy == IO_OPEN_EXCLUSIVE { libc::O_EXCL } else { 0 };
+
+        libc::O_WRONLY | libc::O_CREAT | libc::O_NOFOLLOW | write_flag | exclusive_flag
+    }
+
+    pub fn get_file_mode(&self) -> libc::mode_t
+    {
+        if self.io_file_mode == 0 { IO_FILE_MODE_DEFAULT } else { self.io_file_mode }
+    }
+
+    pub fn get_stream_path(&self, stream_fd : c_int) -> &CStr
+    {
+        let stream_path = match stream_fd
+        {
+            STDIN_FILENO => self.io_path_stdin,
+            STDOUT_FILENO => self.io_path_stdout,
+            _ => self.io_path_stderr
+        };
+
+        unsafe { CStr::from_ptr(stream_path) }
+    }
+
+    fn get_stream_desc(&self, stream_fd : c_int) -> c_int
+    {
+        match stream_fd
+        {
+            STDIN_FILENO => self.io_fd_stdin,
+            STDOUT_FILENO => self.io_fd_stdout,
+            _ => self.io_fd_stderr
+        }
+    }
+
+    /*
+     * Returns pairs of caller and program descriptor numbers, including
+     * redirected stdio streams given as descriptors, in order of application.
+     */
+    pub fn get_fd_map_vec(&self) -> Vec<(c_int, c_int)>
+    {
+        let mut fd_map_vec : Vec<(c_int, c_int)> = Vec::new();
+
+        for stream_fd in [STDIN_FILENO, STDOUT_FILENO, STDERR_FILENO]
+        {
+            if self.get_stream_mode(stream_fd) == Some(IoStreamMode::Pipe)
+            { fd_map_vec.push((self.get_stream_desc(stream_fd), stream_fd)); }
+        }
+
+        if self.io_dup_err_out && self.get_stream_mode(STDOUT_FILENO) == Some(IoStreamMode::Pipe)
+        { fd_map_vec.push((self.io_fd_stdout, STDERR_FILENO)); }
+
+        fd_map_vec.extend(self.get_fd_table_vec());
+        fd_map_vec
+    }
+
+    fn get_fd_table_vec(&self) -> Vec<(c_int, c_int)>
+    {
+        let fd_map = unsafe { CStr::from_ptr(self.io_fd_map) }.to_str().unwrap();
+
+        fd_map.split(':')
+            .filter_map(|fd_pair| fd_pair.split_once('='))
+            .filter_map(|(host_fd, child_fd)| Some((host_fd.parse::<c_int>().ok()?, child_fd.parse::<c_int>().ok()?)))
+            .collect()
+    }
+
+    pub fn get_unix_socket_vec(&self) -> Vec<c_int>
+    {
+        let unix_sockets = unsafe { CStr::from_ptr(self.io_unix_sockets) }.to_str().unwrap();
+
+        unix_sockets.split(':')
+            .filter_map(|socket_fd| socket_fd.parse::<c_int>().ok())
+            .collect()
+    }
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct ExecProgLimits
 {
     pub limit_real_time : c_ulonglong, // real execution time
@@ -151,10 +364,361 @@ pub struct ExecProgLimits
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct ExecProgGuard
 {
     pub scmp_enabled : bool,
     pub scmp_deny_common : bool,
+    pub scmp_deny_tier : c_int,              // Tier of denylist, see `SCMP_DENY_TIER_*`
+    pub scmp_path_policy : bool,             // Check paths of `open`, `exec` and `connect` calls
+    pub scmp_path_allow : *const c_char,     // Colon-separated list of allowed path prefixes
+    pub scmp_learn_mode : bool,              // Record system calls used by the program
+    pub scmp_learn_path : *const c_char,     // File to save a learned profile to (optional)
+    pub scmp_profile_path : *const c_char,   // Profile to use as an allowlist (optional)
+    pub scmp_deny_exec : bool,               // Forbid executions after the program started
+    pub scmp_allow_compat : bool,            // Allow 32-bit and x32 ABIs (killed by default)
     pub unshare_common : bool,
-    pub unshare_network : bool
-}
\ No newline at end of file
+    pub unshare_network : bool,
+    pub unshare_namespaces : c_int,          // Namespaces to unshare, see `UNSHARE_NS_*` (user one is mapped to `nobody`)
+    pub unshare_required : c_int,            // Namespaces that must be created, others are optional
+    pub setns_join : *const c_char,          // Colon-separated list of namespace files or descriptors to join
+    pub net_loopback_up : bool,              // Bring up the loopback device in a new network namespace
+    pub mount_root_path : *const c_char,     // New root directory, requires mount and PID namespaces (optional)
+    pub mount_bind_ro : *const c_char,       // Colon-separated list of paths bound read-only into it
+    pub mount_nosuid : bool,                 // Ignore set-user-ID bits and file capabilities in it
+    pub mount_nodev : bool,                  // Forbid access to device files in it
+    pub mount_noexec : bool,                 // Forbid executing files from writable mounts
+    pub mount_tmpfs : bool,                  // Mount tmpfs at `/tmp` and `/dev/shm`, requires mount and PID namespaces
+    pub mount_tmpfs_paths : *const c_char,   // Colon-separated list of other paths to mount tmpfs at
+    pub mount_tmpfs_size : c_ulonglong,      // Size limit of each tmpfs in bytes (0 - kernel default)
+    pub mount_tmpfs_inodes : c_ulonglong,    // Inodes limit of each tmpfs (0 - kernel default)
+    pub mount_dev : bool,                    // Mount a minimal `/dev`, requires mount and PID namespaces
+    pub landlock_enabled : bool,             // Restrict filesystem access using Landlock, if supported
+    pub landlock_read_paths : *const c_char, // Colon-separated list of paths the program may read
+    pub priv_no_new_privs : bool,            // Forbid gaining privileges by executing set-user-ID binaries
+    pub priv_drop_caps : bool,               // Drop all capabilities, including bounding set, lock secure bits
+    pub priv_not_dumpable : bool,            // Make the child process not dumpable until the program starts
+    pub uid_pool_first : c_uint,             // First user ID of a pool, leased to each execution with a group of the same ID
+    pub uid_pool_size : c_uint,              // Number of user IDs in the pool (0 - disabled)
+    pub signals_ignored : c_ulonglong        // Signals ignored by the program, as bits `1 << (signal - 1)`
+}
+
+impl ExecProgGuard
+{
+    fn check_ptrs(&self) -> bool
+    {
+        !self.scmp_path_allow.is_null()
+            && !self.scmp_learn_path.is_null()
+            && !self.scmp_profile_path.is_null()
+    }
+
+    pub fn verify(&self) -> bool
+    {
+        // Filesystem layout is built even if SECCOMP is disabled
+        if self.mount_root_path.is_null() || self.mount_bind_ro.is_null() || self.mount_tmpfs_paths.is_null() { return false; }
+
+        // New root directory must be present and needs a new mount namespace
+        if let Some(root_path) = self.get_mount_root_path()
+        {
+            if !root_path.is_absolute() || !root_path.is_dir() { return false; }
+            if !self.get_mount_bind_ro_vec().iter().all(|path| path.is_absolute()) { return false; }
+        }
+
+        // Tmpfs instances and `/dev` are mounted in a new mount namespace
+        let tmpfs_paths = self.get_mount_tmpfs_vec();
+        if !tmpfs_paths.is_empty() && !tmpfs_paths.iter().all(|path| path.is_absolute())
+        { return false; }
+
+        // Filesystem layout is built by the init process of a PID namespace, in a new mount namespace
+        let mount_layout = self.get_mount_root_path().is_some() || !tmpfs_paths.is_empty() || self.mount_dev;
+        if mount_layout && self.get_unshare_namespaces() & (UNSHARE_NS_MOUNT | UNSHARE_NS_PID) != UNSHARE_NS_MOUNT | UNSHARE_NS_PID
+        { return false; }
+
+        // Namespaces to join must be of different types, and they are not laid out nor unshared
+        if self.setns_join.is_null() { return false; }
+        let Some(setns_namespaces) = self.get_setns_namespaces() else { return false };
+        if mount_layout && setns_namespaces & (UNSHARE_NS_MOUNT | UNSHARE_NS_PID) != 0 { return false; }
+        if self.unshare_required & setns_namespaces != 0 { return false; }
+
+        // Actions of SIGKILL and SIGSTOP can't be changed
+        if self.signals_ignored & (1 << (libc::SIGKILL - 1) | 1 << (libc::SIGSTOP - 1)) != 0 { return false; }
+
+        // Loopback device is brought up only in a new network namespace
+        if self.net_loopback_up && self.get_unshare_namespaces() & UNSHARE_NS_NET == 0 { return false; }
+
+        // Only known namespaces can be unshared, and required ones must be requested
+        if self.unshare_namespaces & !UNSHARE_NS_ALL != 0 || self.unshare_required & !self.unshare_namespaces != 0
+        { return false; }
+
+        // Paths readable under Landlock must be absolute
+        if self.landlock_read_paths.is_null() { return false; }
+        if self.landlock_enabled && !self.get_landlock_read_vec().iter().all(|path| path.is_absolute()) { return false; }
+
+        // Pool of user IDs must not contain `root`, nor overflow
+        if self.uid_pool_size > 0 && (self.uid_pool_first == 0 || self.uid_pool_first.checked_add(self.uid_pool_size).is_none())
+        { return false; }
+
+        if !self.scmp_enabled { return !self.scmp_path_policy && !self.scmp_learn_mode && !self.scmp_deny_exec; }
+        if !self.check_ptrs() { return false; }
+
+        // Denylist tier must be a known one
+        if !crate::seccomp_tiers::is_valid_deny_tier(self.scmp_deny_tier) { return false; }
+
+        // Supervisor checks system calls of the native ABI only
+        if self.scmp_allow_compat && (self.scmp_path_policy || self.scmp_learn_mode || self.scmp_deny_exec)
+        { return false; }
+
+        // All allowed path prefixes must be absolute
+        if self.scmp_path_policy && !self.get_path_allow_vec().iter().all(|path| path.is_absolute())
+        { return false; }
+
+        // If a profile is specified, it must be present
+        let profile_path = unsafe { CStr::from_ptr(self.scmp_profile_path) };
+        if !profile_path.to_bytes().is_empty() && !Path::new(profile_path.to_str().unwrap()).is_file()
+        { return false; }
+
+        // All checks passed
+        true
+    }
+
+    pub fn get_path_allow_vec(&self) -> Vec<PathBuf>
+    {
+        let path_allow = unsafe { CStr::from_ptr(self.scmp_path_allow) }.to_str().unwrap();
+
+        path_allow.split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /* Namespaces to unshare, including the ones enabled by the `unshare_*` flags */
+    pub fn get_unshare_namespaces(&self) -> c_int
+    {
+        self.unshare_namespaces | self.get_unshare_flags()
+    }
+
+    /* Namespaces that must be created: the ones enabled by the `unshare_*` flags are always required */
+    pub fn get_unshare_required(&self) -> c_int
+    {
+        let mut unshare_required = self.unshare_required | self.get_unshare_flags();
+        if self.get_mount_root_path().is_some() || !self.get_mount_tmpfs_vec().is_empty() || self.mount_dev
+        { unshare_required |= UNSHARE_NS_MOUNT | UNSHARE_NS_PID; }
+        unshare_required
+    }
+
+    pub fn get_setns_join_vec(&self) -> Vec<String>
+    {
+        let setns_join = unsafe { CStr::from_ptr(self.setns_join) }.to_str().unwrap();
+
+        setns_join.split(':')
+            .filter(|setns_entry| !setns_entry.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /* Types of namespaces to join (see `UNSHARE_NS_*`), if all of them are present and of different types */
+    pub fn get_setns_namespaces(&self) -> Option<c_int>
+    {
+        let mut setns_namespaces = 0;
+
+        for setns_entry in self.get_setns_join_vec()
+        {
+            // Namespace files must be absolute paths, unless descriptors are given
+            if setns_entry.parse::<c_int>().is_err() && !Path::new(&setns_entry).is_absolute() { return None; }
+
+            let (namespace_fd, is_opened) = crate::sandboxing_features::open_namespace(&setns_entry)?;
+            let namespace_type = crate::sandboxing_features::get_namespace_type(namespace_fd);
+            if is_opened { unsafe { libc::close(namespace_fd) }; }
+
+            match namespace_type {
+                Some(namespace_type) if setns_namespaces & namespace_type == 0 => setns_namespaces |= namespace_type,
+                _ => return None
+            }
+        }
+
+        Some(setns_namespaces)
+    }
+
+    fn get_unshare_flags(&self) -> c_int
+    {
+        let mut unshare_flags = 0;
+        if self.unshare_common { unshare_flags |= UNSHARE_NS_COMMON; }
+        if self.unshare_network { unshare_flags |= UNSHARE_NS_NET; }
+        unshare_flags
+    }
+
+    pub fn get_mount_root_path(&self) -> Option<PathBuf>
+    {
+        let root_path = unsafe { CStr::from_ptr(self.mount_root_path) }.to_str().unwrap();
+        if root_path.is_empty() { None } else { Some(PathBuf::from(root_path)) }
+    }
+
+    pub fn get_mount_bind_ro_vec(&self) -> Vec<PathBuf>
+    {
+        let bind_ro = unsafe { CStr::from_ptr(self.mount_bind_ro) }.to_str().unwrap();
+
+        bind_ro.split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    pub fn get_mount_tmpfs_vec(&self) -> Vec<PathBuf>
+    {
+        let tmpfs_paths = unsafe { CStr::from_ptr(self.mount_tmpfs_paths) }.to_str().unwrap();
+        let tmpfs_paths = tmpfs_paths.split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+
+        let tmpfs_common = if self.mount_tmpfs { vec![PathBuf::from("/tmp"), PathBuf::from("/dev/shm")] } else { vec![] };
+        tmpfs_common.into_iter().chain(tmpfs_paths).collect()
+    }
+
+    pub fn get_landlock_read_vec(&self) -> Vec<PathBuf>
+    {
+        let read_paths = unsafe { CStr::from_ptr(self.landlock_read_paths) }.to_str().unwrap();
+
+        read_paths.split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::ffi::CString;
+    use libc::{c_char, c_int, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
+    use crate::constants::{IO_MODE_APPEND, IO_MODE_AUTO, IO_MODE_FILE, IO_MODE_INHERIT, IO_MODE_NULL, IO_MODE_PIPE};
+    use crate::constants::{IO_OPEN_APPEND, IO_OPEN_EXCLUSIVE, IO_OPEN_TRUNCATE};
+    use super::{ExecProgIO, IoStreamMode};
+
+    fn to_c_string(value: &str) -> *const c_char
+    { CString::new(value).unwrap().into_raw() }
+
+    /* Request redirecting stdout to a file, other fields can be changed by tests */
+    fn make_io() -> ExecProgIO
+    {
+        ExecProgIO {
+            io_redirected: true,
+            io_path_stdin: to_c_string(""),
+            io_path_stdout: to_c_string("out.txt"),
+            io_path_stderr: to_c_string(""),
+            io_dup_err_out: false,
+            io_unix_sockets: to_c_string(""),
+            io_keep_fds: to_c_string(""),
+            io_fd_stdin: -1,
+            io_fd_stdout: -1,
+            io_fd_stderr: -1,
+            io_fd_map: to_c_string(""),
+            io_mode_stdin: IO_MODE_AUTO,
+            io_mode_stdout: IO_MODE_AUTO,
+            io_mode_stderr: IO_MODE_AUTO,
+            io_open_policy: IO_OPEN_TRUNCATE,
+            io_file_mode: 0
+        }
+    }
+
+    fn make_pipe() -> [c_int; 2]
+    {
+        let mut pipe_fds = [-1; 2];
+        assert_eq!(unsafe { libc::pipe2(pipe_fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        pipe_fds
+    }
+
+    fn close_pipe(pipe_fds : [c_int; 2])
+    { pipe_fds.iter().for_each(|&pipe_fd| unsafe { libc::close(pipe_fd); }); }
+
+    #[test]
+    fn verify_stream_modes()
+    {
+        let pipe_fds = make_pipe();
+        assert!(make_io().verify());
+
+        // Each mode needs a path, a descriptor or nothing
+        assert!(!ExecProgIO { io_mode_stderr: IO_MODE_FILE, ..make_io() }.verify());
+        assert!(ExecProgIO { io_mode_stderr: IO_MODE_FILE, io_path_stderr: to_c_string("err.txt"), ..make_io() }.verify());
+        assert!(ExecProgIO { io_mode_stdout: IO_MODE_APPEND, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdin: IO_MODE_APPEND, io_path_stdin: to_c_string("in.txt"), ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdout: IO_MODE_NULL, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdout: IO_MODE_INHERIT, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdout: IO_MODE_PIPE, ..make_io() }.verify());
+        assert!(ExecProgIO { io_mode_stdout: IO_MODE_PIPE, io_path_stdout: to_c_string(""), io_fd_stdout: pipe_fds[1], ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdout: IO_MODE_PIPE, io_path_stdout: to_c_string(""), io_fd_stdout: STDOUT_FILENO, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_mode_stdout: 6, ..make_io() }.verify());
+
+        // Nothing is redirected, unless modes are chosen explicitly
+        assert!(!ExecProgIO { io_path_stdout: to_c_string(""), ..make_io() }.verify());
+        assert!(ExecProgIO { io_path_stdout: to_c_string(""), io_mode_stdin: IO_MODE_NULL, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_redirected: false, io_mode_stdout: IO_MODE_NULL, ..make_io() }.verify());
+
+        // Input files must exist
+        assert!(!ExecProgIO { io_path_stdin: to_c_string("/nonexistent/in.txt"), ..make_io() }.verify());
+
+        close_pipe(pipe_fds);
+    }
+
+    #[test]
+    fn verify_dup_err_out()
+    {
+        assert!(ExecProgIO { io_dup_err_out: true, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_dup_err_out: true, io_path_stderr: to_c_string("err.txt"), ..make_io() }.verify());
+        assert!(!ExecProgIO { io_dup_err_out: true, io_mode_stdout: IO_MODE_NULL, io_path_stdout: to_c_string(""), io_mode_stdin: IO_MODE_NULL, ..make_io() }.verify());
+    }
+
+    #[test]
+    fn verify_open_policy()
+    {
+        assert!(ExecProgIO { io_open_policy: IO_OPEN_EXCLUSIVE, io_file_mode: 0o600, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_open_policy: 3, ..make_io() }.verify());
+        assert!(!ExecProgIO { io_file_mode: 0o4755, ..make_io() }.verify());
+    }
+
+    #[test]
+    fn output_flags_combine_policy_and_mode()
+    {
+        let output_flags = |open_policy, append_mode| ExecProgIO { io_open_policy: open_policy, ..make_io() }.get_output_flags(append_mode)
+            & (libc::O_TRUNC | libc::O_APPEND | libc::O_EXCL);
+
+        assert_eq!(output_flags(IO_OPEN_TRUNCATE, false), libc::O_TRUNC);
+        assert_eq!(output_flags(IO_OPEN_TRUNCATE, true), libc::O_APPEND);
+        assert_eq!(output_flags(IO_OPEN_APPEND, false), libc::O_APPEND);
+        assert_eq!(output_flags(IO_OPEN_EXCLUSIVE, false), libc::O_TRUNC | libc::O_EXCL);
+        assert_eq!(output_flags(IO_OPEN_EXCLUSIVE, true), libc::O_APPEND | libc::O_EXCL);
+
+        let output_flags = make_io().get_output_flags(false);
+        assert_eq!(output_flags & (libc::O_WRONLY | libc::O_CREAT | libc::O_NOFOLLOW), libc::O_WRONLY | libc::O_CREAT | libc::O_NOFOLLOW);
+        assert_eq!(make_io().get_file_mode(), 0o644);
+    }
+
+    #[test]
+    fn fd_map_vec_includes_streams()
+    {
+        let [read_fd, write_fd] = make_pipe();
+        let fd_map = to_c_string(&format!("{}=10:{}=11", read_fd, write_fd));
+
+        let exec_prog_io = ExecProgIO {
+            io_mode_stdout: IO_MODE_PIPE,
+            io_path_stdout: to_c_string(""),
+            io_fd_stdout: write_fd,
+            io_dup_err_out: true,
+            io_fd_map: fd_map,
+            ..make_io()
+        };
+
+        assert!(exec_prog_io.verify());
+        assert!(exec_prog_io.get_stream_mode(STDIN_FILENO) == Some(IoStreamMode::Null));
+        assert_eq!(exec_prog_io.get_fd_map_vec(), vec![(write_fd, STDOUT_FILENO), (write_fd, STDERR_FILENO), (read_fd, 10), (write_fd, 11)]);
+
+        // Targets must be unique, above stdio streams and not clash with passed descriptors, sources must be open
+        let fd_map_invalid = [format!("{}=10:{}=10", read_fd, write_fd), format!("{}=2", read_fd), format!("{}=10:x", read_fd), String::from("1000=10")];
+        for fd_map in fd_map_invalid
+        { assert!(!ExecProgIO { io_fd_map: to_c_string(&fd_map), ..make_io() }.verify()); }
+
+        let keep_fds = to_c_string(&read_fd.to_string());
+        assert!(!ExecProgIO { io_fd_map: to_c_string(&format!("{}={}", write_fd, read_fd)), io_keep_fds: keep_fds, ..make_io() }.verify());
+
+        close_pipe([read_fd, write_fd]);
+    }
+}
@@ -17,7 +17,7 @@
  */
 
 use libc::{c_int, c_ulonglong};
-use crate::constants::{TIME_MULTIPLIER, KILL_REASON_UNSET};
+use crate::constants::{TIME_MULTIPLIER, KILL_REASON_UNSET, SETUP_FAILURE_NONE};
 
 #[repr(C)]
 pub struct ProcExecResult
@@ -26,6 +26,13 @@ pub struct ProcExecResult
     pub exit_sign: c_int,
     pub is_killed: bool,
     pub kill_reason : c_int,
+    pub scmp_denied : c_ulonglong, // Count of system calls denied by a path-based policy
+    pub setup_failure : c_int,     // Failed setup stage, see `SETUP_FAILURE_*`
+    pub setup_errno : c_int,       // Error number of the failed setup stage
+    pub landlock_abi : c_int,      // Landlock ABI version used to restrict the program (0 - not restricted)
+    pub landlock_access : c_ulonglong, // Filesystem access rights handled by Landlock, see `linux/landlock.h`
+    pub unshare_created : c_int,   // Namespaces created for the program, see `UNSHARE_NS_*`
+    pub group_leftover : bool,     // Processes of the killed group were not reaped in time
     pub res_usage: ProcResUsage
 }
 
@@ -37,7 +44,14 @@ impl ProcExecResult {
             exit_sign: -1,
             res_usage: ProcResUsage::new(),
             is_killed: false,
-            kill_reason: KILL_REASON_UNSET
+            kill_reason: KILL_REASON_UNSET,
+            scmp_denied: 0,
+            setup_failure: SETUP_FAILURE_NONE,
+            setup_errno: 0,
+            landlock_abi: 0,
+            landlock_access: 0,
+            unshare_created: 0,
+            group_leftover: false
         }
     }
 }
@@ -17,37 +17,145 @@
  */
 
 use std::ffi::{CStr, CString};
-use libc::{c_char, c_int, c_ulonglong, rlim64_t, rlimit64, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
-use syscallz::Syscall;
+use std::mem::{size_of, MaybeUninit};
+use libc::{c_int, c_ulonglong, gid_t, pid_t, rlim64_t, rlimit64, uid_t, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
 use crate::{ExecProgGuard, ExecProgInfo, ExecProgIO, ExecProgLimits, SYS_EXEC_FAILED};
-use crate::constants::{SYS_EXEC_OK, TIME_MULTIPLIER};
+use crate::request_structs::IoStreamMode;
+use crate::constants::{SETUP_FAILURE_CREDENTIALS, SETUP_FAILURE_IO_STREAMS, SETUP_FAILURE_NAMESPACES, TIME_MULTIPLIER, USER_NS_NOBODY_ID};
+use crate::constants::{UNSHARE_NS_CGROUP, UNSHARE_NS_IPC, UNSHARE_NS_MOUNT, UNSHARE_NS_NET, UNSHARE_NS_PID, UNSHARE_NS_TIME, UNSHARE_NS_USER, UNSHARE_NS_UTS};
+use crate::seccomp_filters::ScmpFilters;
+use crate::seccomp_notify::NotifyChannel;
+use crate::setup_channel::SetupChannel;
+
+// Not defined by the `libc` crate yet (Linux 5.6+)
+const CLONE_NEWTIME : c_int = 0x80;
+
+// Returns the type of a namespace file, see `linux/nsfs.h` (Linux 4.11+)
+const NS_GET_NSTYPE : libc::c_ulong = 0xb703;
+
+// Namespaces other than the user one, in the order they are unshared
+const UNSHARE_NS_FLAGS : [(c_int, c_int); 7] = [
+    (UNSHARE_NS_MOUNT, libc::CLONE_NEWNS), (UNSHARE_NS_IPC, libc::CLONE_NEWIPC | libc::CLONE_SYSVSEM),
+    (UNSHARE_NS_UTS, libc::CLONE_NEWUTS), (UNSHARE_NS_PID, libc::CLONE_NEWPID),
+    (UNSHARE_NS_CGROUP, libc::CLONE_NEWCGROUP), (UNSHARE_NS_NET, libc::CLONE_NEWNET),
+    (UNSHARE_NS_TIME, CLONE_NEWTIME)
+];
 
-pub fn unshare_resources(exec_prog_guard : &ExecProgGuard)
+/*
+ * Unshare system resources so this process and its child processes won't be able to
+ * do some things related to other processes and actions running in the system. Each
+ * namespace is unshared on its own, so an unsupported one fails only if it is required.
+ * Namespaces that were created are reported to the parent process.
+ *
+ * Note that unsharing namespaces requires CAP_SYS_ADMIN capability
+ * of a caller, unless the user namespace was unshared before.
+ */
+pub fn unshare_resources(exec_prog_guard : &ExecProgGuard, setns_namespaces : c_int, setup_channel : &SetupChannel) -> c_int
 {
+    // Joined namespaces are not unshared
+    let unshare_namespaces = exec_prog_guard.get_unshare_namespaces() & !setns_namespaces;
+    let unshare_required = exec_prog_guard.get_unshare_required() & !setns_namespaces;
+    let mut unshare_created = 0;
+
     /*
-     * Unshare system resources so this process and its child
-     * processes won't be able to do some things related to
-     * other processes and actions running in the system.
-     *
-     * Note that some of enforced `unshare` system call
-     * policies require CAP_SYS_ADMIN capability of a caller.
+     * Unshare user namespace before others, so the process gets all capabilities inside
+     * of it and can unshare other namespaces without CAP_SYS_ADMIN in the host system.
+     * Current user and group are mapped to `nobody` inside of the namespace.
      */
-    if exec_prog_guard.unshare_common
+    if unshare_namespaces & UNSHARE_NS_USER != 0
     {
-        unsafe {
-            let result = libc::unshare(
-                libc::CLONE_NEWNS | libc::CLONE_NEWIPC
-                    | libc::CLONE_NEWUTS | libc::CLONE_NEWPID
-                    | libc::CLONE_NEWCGROUP | libc::CLONE_SYSVSEM);
-            // Panic if system call execution failed
-            if result == SYS_EXEC_FAILED
-            { crate::helper_functions::panic_on_syscall!("unshare"); }
+        let (host_uid, host_gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+
+        // Changing credentials makes `/proc/self` files owned by root, until the process is dumpable again
+        if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("prctl"); }
+
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER) } != SYS_EXEC_FAILED
+        {
+            // Unprivileged processes must deny `setgroups` before they can map groups
+            if !write_proc_self_file("setgroups", "deny")
+                || !write_proc_self_file("uid_map", &format!("{} {} 1", USER_NS_NOBODY_ID, host_uid))
+                || !write_proc_self_file("gid_map", &format!("{} {} 1", USER_NS_NOBODY_ID, host_gid))
+            { setup_channel.report_failure(SETUP_FAILURE_NAMESPACES); }
+
+            unshare_created |= UNSHARE_NS_USER;
         }
+        else if unshare_required & UNSHARE_NS_USER != 0
+        { setup_channel.report_failure(SETUP_FAILURE_NAMESPACES); }
+    }
+
+    for (unshare_namespace, clone_flags) in UNSHARE_NS_FLAGS
+    {
+        if unshare_namespaces & unshare_namespace == 0 { continue; }
+
+        if unsafe { libc::unshare(clone_flags) } != SYS_EXEC_FAILED
+        { unshare_created |= unshare_namespace; }
+        else if unshare_required & unshare_namespace != 0
+        { setup_channel.report_failure(SETUP_FAILURE_NAMESPACES); }
+    }
+
+    setup_channel.report_namespaces(unshare_created);
+    unshare_created
+}
+
+/*
+ * [CHILD] Joins existing namespaces, given as namespace files (in `/proc/<pid>/ns`) or
+ * descriptors of them, instead of unsharing new ones. User namespace is joined first,
+ * so others are joined with capabilities in it. Namespace type of each file is checked
+ * once again, so it can't be replaced after the policy was compiled.
+ */
+pub fn join_namespaces(exec_prog_guard : &ExecProgGuard, setup_channel : &SetupChannel)
+{
+    let mut namespace_fds : Vec<(c_int, bool)> = exec_prog_guard.get_setns_join_vec().iter()
+        .map(|setns_entry| open_namespace(setns_entry).unwrap_or_else(|| setup_channel.report_failure(SETUP_FAILURE_NAMESPACES)))
+        .collect();
+
+    namespace_fds.sort_by_key(|(namespace_fd, _)| get_namespace_type(*namespace_fd) != Some(UNSHARE_NS_USER));
+
+    for (namespace_fd, is_opened) in namespace_fds
+    {
+        let clone_flags = get_namespace_type(namespace_fd).and_then(get_clone_flags)
+            .unwrap_or_else(|| setup_channel.report_failure(SETUP_FAILURE_NAMESPACES));
+
+        if unsafe { libc::setns(namespace_fd, clone_flags & !libc::CLONE_SYSVSEM) } == SYS_EXEC_FAILED
+        { setup_channel.report_failure(SETUP_FAILURE_NAMESPACES); }
+
+        // Descriptors of the caller must not be inherited by the program
+        if is_opened { unsafe { libc::close(namespace_fd) }; }
+        else { unsafe { libc::fcntl(namespace_fd, libc::F_SETFD, libc::FD_CLOEXEC) }; }
     }
+}
+
+/* Returns a descriptor of a namespace file, and whether it was opened (not given by the caller) */
+pub fn open_namespace(setns_entry : &str) -> Option<(c_int, bool)>
+{
+    if let Ok(namespace_fd) = setns_entry.parse::<c_int>()
+    { return (unsafe { libc::fcntl(namespace_fd, libc::F_GETFD) } != SYS_EXEC_FAILED).then_some((namespace_fd, false)); }
+
+    let namespace_path = CString::new(setns_entry).ok()?;
+    let namespace_fd = unsafe { libc::open(namespace_path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+    (namespace_fd != SYS_EXEC_FAILED).then_some((namespace_fd, true))
+}
+
+/* Returns a type of a namespace (see `UNSHARE_NS_*`), its descriptor refers to */
+pub fn get_namespace_type(namespace_fd : c_int) -> Option<c_int>
+{
+    let namespace_type = unsafe { libc::ioctl(namespace_fd, NS_GET_NSTYPE) };
+    if namespace_type == libc::CLONE_NEWUSER { return Some(UNSHARE_NS_USER); }
+
+    UNSHARE_NS_FLAGS.iter().find(|(_, clone_flags)| namespace_type > 0 && clone_flags & namespace_type == namespace_type)
+        .map(|(unshare_namespace, _)| *unshare_namespace)
+}
+
+fn get_clone_flags(unshare_namespace : c_int) -> Option<c_int>
+{
+    if unshare_namespace == UNSHARE_NS_USER { return Some(libc::CLONE_NEWUSER); }
+    UNSHARE_NS_FLAGS.iter().find(|(namespace, _)| *namespace == unshare_namespace).map(|(_, clone_flags)| *clone_flags)
+}
 
-    // Unshare network namespace (requires CAP_SYS_ADMIN)
-    if exec_prog_guard.unshare_network && unsafe { libc::unshare(libc::CLONE_NEWNET) } == SYS_EXEC_FAILED
-    { crate::helper_functions::panic_on_syscall!("unshare"); }
+fn write_proc_self_file(file_name : &str, content : &str) -> bool
+{
+    std::fs::write(format!("/proc/self/{}", file_name), content).is_ok()
 }
 
 pub fn set_work_dir(exec_prog_info : &ExecProgInfo)
@@ -57,6 +165,40 @@ pub fn set_work_dir(exec_prog_info : &ExecProgInfo)
     { crate::helper_functions::panic_on_syscall!("chdir"); }
 }
 
+// Attempts to wait for processes of a killed group, which are reaped by their new parent
+const GROUP_WAIT_ATTEMPTS : c_int = 100;
+const GROUP_WAIT_INTERVAL : u64 = 10;
+
+/*
+ * Child process leads its own session and process group (its ID equals to the child process
+ * ID), which contains all processes of the program, unless they start new groups. It may not
+ * have called `setsid` yet, so the child process itself is killed separately.
+ */
+pub fn kill_process_group(child_pid : pid_t)
+{
+    unsafe {
+        libc::kill(-child_pid, libc::SIGKILL);
+        libc::kill(child_pid, libc::SIGKILL);
+    }
+}
+
+/*
+ * Waits until all processes of a killed group are reaped. Processes can't be forked while
+ * the group is killed, so they are only checked, not to kill another group with the same ID.
+ * Returns `false` if some of them are still there (for example, a new parent doesn't reap
+ * them), so the caller knows the group ID may be still in use.
+ */
+pub fn wait_process_group(child_pid : pid_t) -> bool
+{
+    for _ in 0..GROUP_WAIT_ATTEMPTS
+    {
+        if unsafe { libc::kill(-child_pid, 0) } == SYS_EXEC_FAILED { return true; }
+        std::thread::sleep(std::time::Duration::from_millis(GROUP_WAIT_INTERVAL));
+    }
+
+    false
+}
+
 pub fn kill_on_parent_exit()
 {
     if unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) } == SYS_EXEC_FAILED
@@ -64,81 +206,350 @@ pub fn kill_on_parent_exit()
 }
 
 /*
- * This function covers I/O streams redirection to files stored on a disk or other streams.
- * We use system APIs to ensure that all things will work no matter of the situation.
+ * New PID namespace applies only to the children of a process that unshared it, and
+ * the first of them becomes its init process (PID 1). Such process must reap orphaned
+ * processes, so we can't execute the program in it. Instead, the init process builds
+ * the filesystem layout (see `mount_layout`) if the mount namespace was created along
+ * with it, forks the program and forwards signals to it, while the current process waits
+ * for the init process and exits the same way the program did. Killing the current
+ * process kills the init, which tears down the whole namespace. The program reports
+ * its PID as the parent process sees it, using `/proc` of the host opened beforehand.
  */
 
-pub fn redirect_io_streams(exec_prog_io : &ExecProgIO)
+const INIT_FORWARD_SIGNALS : [c_int; 7] = [
+    libc::SIGHUP, libc::SIGINT, libc::SIGQUIT, libc::SIGTERM,
+    libc::SIGUSR1, libc::SIGUSR2, libc::SIGCONT
+];
+
+pub fn init_pid_namespace(exec_prog_info : &ExecProgInfo, exec_prog_guard : &ExecProgGuard, mount_layout : bool, setup_channel : &SetupChannel)
 {
-    if !exec_prog_io.io_redirected { return; }
+    // Program wait status is sent by the init process over a pipe
+    let mut status_pipe : [c_int; 2] = [0; 2];
+    if unsafe { libc::pipe2(status_pipe.as_mut_ptr(), libc::O_CLOEXEC) } == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("pipe2"); }
 
-    let io_path_stdin  : &CStr = unsafe { CStr::from_ptr(exec_prog_io.io_path_stdin) };
-    let io_path_stdout : &CStr = unsafe { CStr::from_ptr(exec_prog_io.io_path_stdout) };
-    let io_path_stderr : &CStr = unsafe { CStr::from_ptr(exec_prog_io.io_path_stderr) };
+    let [status_read, status_write] = status_pipe;
 
-    // Standard input stream redirection
-    if !io_path_stdin.to_bytes().is_empty()
+    let init_pid = unsafe { libc::fork() };
+    if init_pid == SYS_EXEC_FAILED { crate::helper_functions::panic_on_syscall!("fork"); }
+    if init_pid > 0
     {
-        let file_fd = try_get_fd(exec_prog_io.io_path_stdin, libc::O_RDONLY);
-        try_dup_fd(file_fd, STDIN_FILENO);
+        unsafe { libc::close(status_write) };
+        wait_init_process(init_pid, status_read);
+    }
+
+    // The init process must not outlive the process that waits for it, which
+    // closes the read end of the pipe when it exits before the signal is set
+    unsafe { libc::close(status_read) };
+    kill_on_parent_exit();
+
+    let mut status_poll = libc::pollfd { fd: status_write, events: 0, revents: 0 };
+    if unsafe { libc::poll(&mut status_poll, 1, 0) } != 0 { unsafe { libc::_exit(SYS_EXEC_FAILED) } }
+
+    // Namespace of the host `/proc` is the one of the parent process, unlike a new one mounted by the layout
+    let proc_path = CString::new("/proc").unwrap();
+    let proc_fd = unsafe { libc::open(proc_path.as_ptr(), libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC) };
+
+    if mount_layout { crate::mount_layout::init_mount_layout(exec_prog_info, exec_prog_guard, setup_channel); }
+
+    // Signals are blocked before the fork, so none of them is lost
+    let mut init_sigset = MaybeUninit::<libc::sigset_t>::uninit();
+    let mut orig_sigset = MaybeUninit::<libc::sigset_t>::uninit();
+
+    unsafe {
+        libc::sigemptyset(init_sigset.as_mut_ptr());
+        libc::sigaddset(init_sigset.as_mut_ptr(), libc::SIGCHLD);
+        for signal in INIT_FORWARD_SIGNALS { libc::sigaddset(init_sigset.as_mut_ptr(), signal); }
     }
-    else { dup_to_dev_null(STDIN_FILENO); }
 
-    // Standard output stream redirection
-    if !io_path_stdout.to_bytes().is_empty()
+    if unsafe { libc::sigprocmask(libc::SIG_BLOCK, init_sigset.as_ptr(), orig_sigset.as_mut_ptr()) } == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("sigprocmask"); }
+
+    let program_pid = unsafe { libc::fork() };
+    if program_pid == SYS_EXEC_FAILED { crate::helper_functions::panic_on_syscall!("fork"); }
+    if program_pid == 0
     {
-        let file_fd = try_get_fd(exec_prog_io.io_path_stdout, libc::O_WRONLY);
-        try_dup_fd(file_fd, STDOUT_FILENO);
+        // Program process continues with the original signal mask
+        unsafe { libc::close(status_write) };
+        if unsafe { libc::sigprocmask(libc::SIG_SETMASK, orig_sigset.as_ptr(), std::ptr::null_mut()) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("sigprocmask"); }
+
+        if let Some(program_pid) = get_host_pid(proc_fd) { setup_channel.report_program_pid(program_pid); }
+        unsafe { libc::close(proc_fd) };
+        return;
+    }
+
+    unsafe { libc::close(proc_fd) };
+    run_init_process(program_pid, status_write, unsafe { init_sigset.assume_init() });
+}
+
+/* Reads PID of the current process from the `self` link of a `/proc` instance */
+fn get_host_pid(proc_fd : c_int) -> Option<pid_t>
+{
+    if proc_fd == SYS_EXEC_FAILED { return None; }
+
+    let self_path = CString::new("self").unwrap();
+    let mut pid_buffer = [0u8; 16];
+
+    let read_size = unsafe { libc::readlinkat(proc_fd, self_path.as_ptr(), pid_buffer.as_mut_ptr() as *mut libc::c_char, pid_buffer.len()) };
+    if read_size <= 0 { return None; }
 
-        // Duplication of STDERR into a new STDOUT FD
-        if exec_prog_io.io_dup_err_out
-        { try_dup_fd(file_fd, STDERR_FILENO); }
+    std::str::from_utf8(&pid_buffer[..read_size as usize]).ok()?.parse().ok()
+}
+
+fn run_init_process(program_pid : pid_t, status_write : c_int, init_sigset : libc::sigset_t) -> !
+{
+    loop {
+        let mut signal : c_int = 0;
+        if unsafe { libc::sigwait(&init_sigset, &mut signal) } != 0 { continue }
+
+        if signal != libc::SIGCHLD
+        { unsafe { libc::kill(program_pid, signal) }; continue; }
+
+        // Reap every terminated process, including the orphaned ones
+        loop {
+            let mut wait_status : c_int = 0;
+            let wait_pid = unsafe { libc::waitpid(-1, &mut wait_status, libc::WNOHANG) };
+            if wait_pid <= 0 { break }
+
+            // Processes left in the namespace are killed when the init process exits
+            if wait_pid == program_pid
+            {
+                unsafe {
+                    libc::write(status_write, &wait_status as *const c_int as *const libc::c_void, size_of::<c_int>());
+                    libc::_exit(0);
+                }
+            }
+        }
+    }
+}
+
+fn wait_init_process(init_pid : pid_t, status_read : c_int) -> !
+{
+    let mut wait_status : c_int = 0;
+    while unsafe { libc::waitpid(init_pid, &mut wait_status, 0) } == SYS_EXEC_FAILED
+    {
+        if nix::errno::errno() != libc::EINTR
+        { crate::helper_functions::panic_on_syscall!("waitpid"); }
     }
-    else { dup_to_dev_null(STDOUT_FILENO); }
 
-    // Standard error stream redirection (if not redirected to STDOUT)
-    if !exec_prog_io.io_dup_err_out
+    // Init process reports the program wait status, unless it was killed before
+    let mut program_status : c_int = 0;
+    let read_size = unsafe { libc::read(status_read, &mut program_status as *mut c_int as *mut libc::c_void, size_of::<c_int>()) };
+    if read_size == size_of::<c_int>() as isize { wait_status = program_status; }
+
+    if libc::WIFSIGNALED(wait_status)
     {
-        if !io_path_stderr.to_bytes().is_empty()
-        {
-            let file_fd = try_get_fd(exec_prog_io.io_path_stderr, libc::O_WRONLY);
-            try_dup_fd(file_fd, STDERR_FILENO);
+        let signal = libc::WTERMSIG(wait_status);
+
+        // Forked process is not dumpable, so raised signal never produces a core dump
+        unsafe {
+            libc::prctl(libc::PR_SET_DUMPABLE, 0);
+            libc::signal(signal, libc::SIG_DFL);
+
+            let mut signal_set = MaybeUninit::<libc::sigset_t>::uninit();
+            libc::sigemptyset(signal_set.as_mut_ptr());
+            libc::sigaddset(signal_set.as_mut_ptr(), signal);
+            libc::sigprocmask(libc::SIG_UNBLOCK, signal_set.as_ptr(), std::ptr::null_mut());
+            libc::raise(signal);
         }
-        else { dup_to_dev_null(STDERR_FILENO); }
     }
 
-    fn dup_to_dev_null(dest_fd: c_int)
+    unsafe { libc::_exit(if libc::WIFEXITED(wait_status) { libc::WEXITSTATUS(wait_status) } else { SYS_EXEC_FAILED }) }
+}
+
+/*
+ * This function covers I/O streams redirection to files stored on a disk or other streams.
+ * We use system APIs to ensure that all things will work no matter of the situation.
+ */
+
+pub fn redirect_io_streams(exec_prog_io : &ExecProgIO, setup_channel : &SetupChannel)
+{
+    if !exec_prog_io.io_redirected { return; }
+
+    // Relative paths are opened beneath the working directory, which is already set
+    let work_dir = CString::new(".").unwrap();
+    let work_dir_fd = unsafe { libc::open(work_dir.as_ptr(), libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC) };
+    if work_dir_fd == SYS_EXEC_FAILED { setup_channel.report_failure(SETUP_FAILURE_IO_STREAMS); }
+
+    for stream_fd in [STDIN_FILENO, STDOUT_FILENO, STDERR_FILENO]
     {
-        let dev_null = CString::new("/dev/null").unwrap();
-        let file_fd = try_get_fd(dev_null.as_ptr(), libc::O_RDWR);
-        try_dup_fd(file_fd, dest_fd);
+        // Duplication of STDERR into a new STDOUT FD (caller-owned one is mapped later)
+        if stream_fd == STDERR_FILENO && exec_prog_io.io_dup_err_out
+        {
+            if exec_prog_io.get_stream_mode(STDOUT_FILENO) != Some(IoStreamMode::Pipe) { try_dup_fd(STDOUT_FILENO, STDERR_FILENO, setup_channel); }
+            continue;
+        }
+
+        let stream_path = exec_prog_io.get_stream_path(stream_fd);
+        let stream_mode = exec_prog_io.get_stream_mode(stream_fd);
+
+        match stream_mode
+        {
+            Some(IoStreamMode::File) if stream_fd == STDIN_FILENO =>
+            {
+                let file_fd = try_get_fd(work_dir_fd, stream_path, libc::O_RDONLY, 0, setup_channel);
+                try_dup_fd(file_fd, stream_fd, setup_channel);
+            },
+            Some(IoStreamMode::File | IoStreamMode::Append) =>
+            {
+                let file_flag = exec_prog_io.get_output_flags(stream_mode == Some(IoStreamMode::Append));
+                let file_fd = open_output_file(work_dir_fd, stream_path, file_flag, exec_prog_io.get_file_mode());
+
+                if file_fd == SYS_EXEC_FAILED { setup_channel.report_failure(SETUP_FAILURE_IO_STREAMS); }
+                try_dup_fd(file_fd, stream_fd, setup_channel);
+            },
+            Some(IoStreamMode::Null) =>
+            {
+                let dev_null = CString::new("/dev/null").unwrap();
+                let file_fd = try_get_fd(work_dir_fd, &dev_null, libc::O_RDWR, 0, setup_channel);
+                try_dup_fd(file_fd, stream_fd, setup_channel);
+            },
+            Some(IoStreamMode::Inherit) | Some(IoStreamMode::Pipe) | None => { /* Kept as is, or mapped later, see `map_caller_fds` */ }
+        }
     }
 
+    unsafe { libc::close(work_dir_fd) };
+
     /* @A lightweight `dup2` system call wrapper */
-    fn try_dup_fd(src_fd: c_int, dst_fd: c_int)
+    fn try_dup_fd(src_fd : c_int, dst_fd : c_int, setup_channel : &SetupChannel)
     {
         if unsafe { libc::dup2(src_fd, dst_fd) } == SYS_EXEC_FAILED
-        { crate::helper_functions::panic_on_syscall!("dup2"); }
+        { setup_channel.report_failure(SETUP_FAILURE_IO_STREAMS); }
     }
     /* @/A lightweight `dup2` system call wrapper */
 
-    fn try_get_fd(file_path: *const c_char, file_flag : c_int) -> c_int
+    fn try_get_fd(work_dir_fd : c_int, file_path : &CStr, file_flag : c_int, file_mode : libc::mode_t, setup_channel : &SetupChannel) -> c_int
     {
-        // Note that O_PATH specifies that we don't need to open a file,
-        // but only get a descriptor pointing at it to use with `dup2`.
-        // Flag O_CREAT indicates that `open` system call must create a
-        // file on the specified path, in case it was not found.
-        let file_fd = unsafe { libc::open(file_path, file_flag | libc::O_CREAT) };
+        // Descriptor is closed on exec, as only its duplicate is used by the program.
+        // Mode is applied only if `open` system call creates a file (with O_CREAT).
+        let file_fd = unsafe { libc::openat(work_dir_fd, file_path.as_ptr(), file_flag | libc::O_CLOEXEC, file_mode as libc::c_uint) };
 
         // Check whether file opened successfully
         if file_fd == SYS_EXEC_FAILED
-        { crate::helper_functions::panic_on_syscall!("open"); }
+        { setup_channel.report_failure(SETUP_FAILURE_IO_STREAMS); }
 
         // Return a file descriptior pointing to file
         file_fd
     }
 }
 
+/*
+ * Kernel ABI of `openat2` system call (Linux 5.6+), see `linux/openat2.h`. It is not
+ * yet exported by the `libc` crate version we use.
+ */
+
+#[repr(C)]
+struct OpenHow
+{
+    flags : u64,
+    mode : u64,
+    resolve : u64
+}
+
+const RESOLVE_BENEATH : u64 = 0x08;
+
+/*
+ * Opens an output file without following a symbolic link at its path, as the program could
+ * plant one to redirect the output of the next run anywhere. Relative paths are resolved
+ * beneath the working directory (`work_dir_fd`), so neither `..` nor a symbolic link can lead
+ * out of it (unless `openat2` is not supported). Absolute paths are chosen by the caller, so
+ * they may pass through symbolic links of the system. Sets `errno` on failure.
+ */
+fn open_output_file(work_dir_fd : c_int, file_path : &CStr, file_flag : c_int, file_mode : libc::mode_t) -> c_int
+{
+    let file_flag = file_flag | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+
+    if !file_path.to_bytes().starts_with(b"/")
+    {
+        let open_how = OpenHow { flags: file_flag as u64, mode: file_mode as u64, resolve: RESOLVE_BENEATH };
+        let file_fd = unsafe { libc::syscall(libc::SYS_openat2, work_dir_fd, file_path.as_ptr(), &open_how, size_of::<OpenHow>()) } as c_int;
+
+        if file_fd != SYS_EXEC_FAILED || nix::errno::errno() != libc::ENOSYS { return file_fd; }
+    }
+
+    unsafe { libc::openat(work_dir_fd, file_path.as_ptr(), file_flag, file_mode as libc::c_uint) }
+}
+
+// Mark descriptors to be closed on exec, instead of closing them (Linux 5.11+)
+const CLOSE_RANGE_CLOEXEC : c_int = 1 << 2;
+
+/*
+ * Descriptors opened by the caller without O_CLOEXEC flag (sockets, log files) would be
+ * inherited by the program, so all of them except for standard streams and descriptors
+ * the caller wants to keep are closed. They are marked to be closed on exec instead, as
+ * the child process still uses some of them (for example, to report a failed setup stage).
+ * If `close_range` is not supported, descriptors listed in `/proc/self/fd` are marked.
+ */
+pub fn close_inherited_fds(exec_prog_io : &ExecProgIO)
+{
+    if unsafe { libc::syscall(libc::SYS_close_range, STDERR_FILENO + 1, c_int::MAX, CLOSE_RANGE_CLOEXEC) } == SYS_EXEC_FAILED as libc::c_long
+    {
+        let Ok(dir_entries) = std::fs::read_dir("/proc/self/fd")
+        else { crate::helper_functions::panic_on_syscall!("close_range"); };
+
+        for inherited_fd in dir_entries.flatten().filter_map(|dir_entry| dir_entry.file_name().to_str()?.parse::<c_int>().ok())
+        { if inherited_fd > STDERR_FILENO { unsafe { libc::fcntl(inherited_fd, libc::F_SETFD, libc::FD_CLOEXEC) }; } }
+    }
+
+    for keep_fd in exec_prog_io.get_keep_fd_vec()
+    {
+        if unsafe { libc::fcntl(keep_fd, libc::F_SETFD, 0) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("fcntl"); }
+    }
+}
+
+/*
+ * Descriptors given by the caller are duplicated to the numbers the program expects. Source
+ * and target numbers may overlap (for example, two descriptors are swapped), so all sources
+ * are copied above the targets first, and these copies are closed on exec.
+ */
+pub fn map_caller_fds(exec_prog_io : &ExecProgIO)
+{
+    let fd_map = exec_prog_io.get_fd_map_vec();
+    let fd_copy_min = fd_map.iter().map(|&(_, child_fd)| child_fd + 1).max().unwrap_or(0);
+
+    let fd_copies : Vec<(c_int, c_int)> = fd_map.iter().map(|&(host_fd, child_fd)| {
+        let fd_copy = unsafe { libc::fcntl(host_fd, libc::F_DUPFD_CLOEXEC, fd_copy_min) };
+        if fd_copy == SYS_EXEC_FAILED { crate::helper_functions::panic_on_syscall!("fcntl"); }
+        (fd_copy, child_fd)
+    }).collect();
+
+    // Descriptors created by `dup2` are not closed on exec
+    for (fd_copy, child_fd) in fd_copies
+    {
+        if unsafe { libc::dup2(fd_copy, child_fd) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("dup2"); }
+    }
+}
+
+// Highest signal number, including real-time signals
+const SIGNAL_MAX : c_int = 64;
+
+/*
+ * Child process inherits the signal mask and ignored signals of the caller (for example,
+ * runtimes ignore SIGPIPE), which are kept by exec, so the program would behave unlike
+ * started by a shell. Every signal is reset to its default action, except for the ones
+ * the caller wants to be ignored, and unblocked. Signals reserved by the C library can't
+ * be changed, so their errors are ignored.
+ */
+pub fn reset_signals(exec_prog_guard : &ExecProgGuard)
+{
+    for signal in 1..=SIGNAL_MAX
+    {
+        if signal == libc::SIGKILL || signal == libc::SIGSTOP { continue; }
+
+        let signal_handler = if exec_prog_guard.signals_ignored & (1 << (signal - 1)) != 0 { libc::SIG_IGN } else { libc::SIG_DFL };
+        unsafe { libc::signal(signal, signal_handler) };
+    }
+
+    let mut signal_set = MaybeUninit::<libc::sigset_t>::uninit();
+    unsafe { libc::sigemptyset(signal_set.as_mut_ptr()) };
+
+    if unsafe { libc::sigprocmask(libc::SIG_SETMASK, signal_set.as_ptr(), std::ptr::null_mut()) } == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("sigprocmask"); }
+}
+
 /*
  * This function covers the enforcement of system resources usage limits and
  * policies for the current (child) process, depending on execution request.
@@ -162,7 +573,7 @@ pub fn set_resource_limits(exec_prog_limits : &ExecProgLimits)
          */
         let mut limit_in_seconds : c_ulonglong;
 
-        if (exec_prog_limits.limit_proc_time % TIME_MULTIPLIER as c_ulonglong) == 0
+        if exec_prog_limits.limit_proc_time.is_multiple_of(TIME_MULTIPLIER as c_ulonglong)
         { limit_in_seconds = exec_prog_limits.limit_proc_time / TIME_MULTIPLIER as c_ulonglong; }
         else { limit_in_seconds = exec_prog_limits.limit_proc_time / TIME_MULTIPLIER as c_ulonglong + 1; }
 
@@ -203,23 +614,98 @@ pub fn set_resource_limits(exec_prog_limits : &ExecProgLimits)
     /* @/Function that utilizes `setrlimit` system call to set resource limit */
 }
 
-pub fn init_set_user_id(exec_prog_info : &ExecProgInfo)
+/*
+ * Users are resolved in the parent process, as `getpwnam` reads files and allocates
+ * memory, which is not safe in a child process forked from a multithreaded one.
+ *
+ * User is specified as `user` or `user:group`, where both parts are either names or
+ * numeric IDs. Without a group, primary group of the user is used, or the group with
+ * the same ID, if a numeric user ID is not present in the system users database.
+ */
+pub fn get_user_ids(exec_as_user : &CStr) -> Option<(uid_t, gid_t)>
 {
-    let username = unsafe { CStr::from_ptr(exec_prog_info.exec_as_user) };
+    let exec_as_user = exec_as_user.to_str().unwrap();
+    if exec_as_user.is_empty() { return None; }
+
+    let (user, group) = match exec_as_user.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (exec_as_user, None)
+    };
 
-    if username.to_bytes().is_empty() { return; }
+    // Get PASSWD information about the user behind the username (or ID)
+    let pwnam = match user.parse::<uid_t>() {
+        Ok(uid) => unsafe { libc::getpwuid(uid) },
+        Err(_) => unsafe { libc::getpwnam(CString::new(user).unwrap().as_ptr()) }
+    };
 
-    // Get PASSWD information about the user behind the username
-    let pwnam = unsafe { libc::getpwnam(username.as_ptr()) };
+    let (uid, primary_gid) = match (unsafe { pwnam.as_ref() }, user.parse::<uid_t>()) {
+        (Some(obj), _) => (obj.pw_uid, obj.pw_gid),
+        (None, Ok(uid)) => (uid, uid),
+        (None, Err(_)) => { panic!("System call 'GETPWNAM' failed: user with specified name was not found!") }
+    };
 
-    let user_info = match unsafe { pwnam.as_ref() } {
-        Some(obj) => obj,
-        None => { panic!("System call 'GETPWNAM' failed: user with specified name was not found!") }
+    let gid = match group.map(|group| (group, group.parse::<gid_t>())) {
+        None => primary_gid,
+        Some((_, Ok(gid))) => gid,
+        Some((group, Err(_))) => match unsafe { libc::getgrnam(CString::new(group).unwrap().as_ptr()).as_ref() } {
+            Some(obj) => obj.gr_gid,
+            None => { panic!("System call 'GETGRNAM' failed: group with specified name was not found!") }
+        }
     };
 
-    // Try to execute SETUID system call on the current process
-    if unsafe { libc::setuid(user_info.pw_uid) } != 0
-    { crate::helper_functions::panic_on_syscall!("setuid"); }
+    Some((uid, gid))
+}
+
+/*
+ * Group IDs are changed first, as it requires privileges dropped by changing user IDs.
+ * Real, effective and saved IDs are changed, as well as supplementary groups are removed,
+ * so the program has nothing in common with the host user (for example, root) anymore.
+ * Unprivileged callers can only run programs as themselves, keeping supplementary groups,
+ * as they are not allowed to remove them (nothing is gained over the caller in this case).
+ */
+pub fn init_set_user_ids(exec_as_ids : Option<(uid_t, gid_t)>, setup_channel : &SetupChannel)
+{
+    let (uid, gid) = match exec_as_ids {
+        Some(exec_as_ids) => exec_as_ids,
+        None => return
+    };
+
+    let (host_uid, host_gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+    if unsafe { libc::setresgid(gid, gid, gid) } == SYS_EXEC_FAILED
+    { setup_channel.report_failure(SETUP_FAILURE_CREDENTIALS); }
+
+    // Unprivileged callers (without `CAP_SETGID`) running programs as themselves keep their groups
+    let keep_groups = unsafe {
+        libc::getgroups(0, std::ptr::null_mut()) != 0 && libc::setgroups(0, std::ptr::null()) == SYS_EXEC_FAILED
+    };
+
+    if keep_groups && (uid != host_uid || gid != host_gid || nix::errno::errno() != libc::EPERM)
+    { setup_channel.report_failure(SETUP_FAILURE_CREDENTIALS); }
+
+    if unsafe { libc::setresuid(uid, uid, uid) } == SYS_EXEC_FAILED
+    { setup_channel.report_failure(SETUP_FAILURE_CREDENTIALS); }
+
+    // Check that all IDs were changed and privileges can't be regained
+    let (mut ruid, mut euid, mut suid) = (0, 0, 0);
+    let (mut rgid, mut egid, mut sgid) = (0, 0, 0);
+
+    let ids_changed = unsafe {
+        libc::getresuid(&mut ruid, &mut euid, &mut suid) != SYS_EXEC_FAILED
+            && libc::getresgid(&mut rgid, &mut egid, &mut sgid) != SYS_EXEC_FAILED
+            && (keep_groups || libc::getgroups(0, std::ptr::null_mut()) == 0)
+    } && [ruid, euid, suid] == [uid; 3] && [rgid, egid, sgid] == [gid; 3];
+
+    let ids_regained = unsafe {
+        (uid != host_uid && (libc::setuid(host_uid) != SYS_EXEC_FAILED || libc::seteuid(host_uid) != SYS_EXEC_FAILED))
+            || (gid != host_gid && uid != 0 && (libc::setgid(host_gid) != SYS_EXEC_FAILED || libc::setegid(host_gid) != SYS_EXEC_FAILED))
+    };
+
+    if !ids_changed || ids_regained
+    {
+        unsafe { *libc::__errno_location() = libc::EPERM };
+        setup_channel.report_failure(SETUP_FAILURE_CREDENTIALS);
+    }
 }
 
 /*
@@ -227,49 +713,34 @@ pub fn init_set_user_id(exec_prog_info : &ExecProgInfo)
  * policies, so child process cannot use system calls, filtered by SECCOMP.
  *
  * Note that usage of this feature requires libseccomp-dev on development machine and
- * enabled support of libseccomp features on the targer computer. Refer to docs of your
- * GNU/Linux distribution on how to enable it.
+ * enabled support of libseccomp features on the targer computer (unless the library is
+ * built with `native-bpf` feature). Refer to docs of your GNU/Linux distribution on how
+ * to enable it. Filters are compiled before `fork` (see `ScmpFilters`), here we load them.
+ *
+ * When a path-based policy or learning mode is requested, an additional filter is installed
+ * first, which passes system calls to the supervisor running in the parent process. If a
+ * profile is specified, only system calls listed in it are allowed, the rest kill a process.
+ * Denylist and profile are loaded as separate filters, the most restrictive action wins.
  */
 
-pub fn init_secure_computing(exec_prog_guard : &ExecProgGuard)
+pub fn init_secure_computing(exec_prog_guard : &ExecProgGuard, scmp_filters : Option<&ScmpFilters>, notify_channel : Option<&NotifyChannel>)
 {
-    if !exec_prog_guard.scmp_enabled { return; }
-
-    // Initialize a new SECCOMP context with defaults to 'Allow' policy
-    let mut ctx = match syscallz::Context::init_with_action(syscallz::Action::Allow) {
-        Ok(ctx) => ctx,
-        Err(err) => { panic!("Cannot initialize SECCOMP context: {}", err) }
+    let scmp_filters = match scmp_filters {
+        Some(scmp_filters) if exec_prog_guard.scmp_enabled => scmp_filters,
+        _ => return
     };
 
-    /* @Prevent process from using common unwanted system calls */
-    if exec_prog_guard.scmp_deny_common
-    {
-        // Generate a list of common unwanted system calls
-        let syscalls_list = [
-            // Deny creating child processes, changing process ownership, etc.
-            Syscall::reboot, Syscall::setuid, Syscall::setgid, Syscall::prctl,
-            Syscall::unshare, Syscall::setrlimit, //Syscall::prlimit64, // Syscall::getrlimit,
-
-            // Operations on per-process timer are denied
-            Syscall::timer_create, Syscall::timer_gettime, Syscall::timer_settime, Syscall::timer_delete,
-            Syscall::timer_getoverrun, Syscall::timerfd_create, Syscall::timerfd_gettime, Syscall::timerfd_settime,
-
-            // Deny making unwanted changes to filesystem
-            Syscall::chdir, Syscall::fchdir, Syscall::chmod, Syscall::fchmod, Syscall::fchmodat,
-            Syscall::chown, Syscall::fchown, Syscall::lchown, Syscall::fchownat
-            //Syscall::link, Syscall::unlink
-        ];
-
-        // Enumerate through common system calls list
-        for sys_call in syscalls_list {
-            // Add a 'KillProcess' action filter to every single system call in the list
-            if let Err(err) = ctx.set_action_for_syscall(syscallz::Action::KillProcess, sys_call)
-            { panic!("Cannot add new SECCOMP filter for system call '{}': {}", sys_call.into_i32(), err) }
-        }
-    }
-    /* @/Prevent process from using common unwanted system calls */
+    // Unprivileged processes can't load SECCOMP filters without this flag
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("prctl"); }
+
+    crate::seccomp_bpf::load_filter(&scmp_filters.arch_filter, 0);
+
+    // Pass system calls to the supervisor in the parent process
+    if let Some(notify_channel) = notify_channel
+    { notify_channel.install_listener(); }
 
     // Try to enforce the SECCOMP policy we built for the current process
-    if let Err(err) = ctx.load()
-    { panic!("SECCOMP policy enforcement failed: {}", err) }
-}
\ No newline at end of file
+    for bpf_program in [&scmp_filters.deny_filter, &scmp_filters.profile_filter].into_iter().flatten()
+    { crate::seccomp_bpf::load_filter(bpf_program, 0); }
+}
@@ -0,0 +1,119 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::{c_int, c_uint, c_ulong, sock_filter, sock_fprog};
+use crate::SYS_EXEC_FAILED;
+
+/*
+ * Kernel ABI definitions of the `seccomp` system call, see `linux/seccomp.h`.
+ * Some of them are not yet exported by the `libc` crate version we use.
+ */
+
+pub(crate) const SECCOMP_SET_MODE_FILTER : c_uint = 1;
+pub(crate) const SECCOMP_FILTER_FLAG_NEW_LISTENER : c_ulong = 1 << 3;
+pub(crate) const SECCOMP_RET_USER_NOTIF : u32 = 0x7fc00000;
+
+// Offsets of `struct seccomp_data` fields inspected by BPF programs
+pub(crate) const SECCOMP_DATA_NR_OFFSET : u32 = 0;
+pub(crate) const SECCOMP_DATA_ARCH_OFFSET : u32 = 4;
+pub(crate) const SECCOMP_DATA_ARGS_OFFSET : u32 = 16;
+
+// Audit architecture identifiers of the platform the library is built for and its compatibility ABIs
+#[cfg(target_arch = "x86_64")]
+pub(crate) const AUDIT_ARCH_NATIVE : u32 = 0xC000003E;
+#[cfg(target_arch = "x86_64")]
+pub(crate) const AUDIT_ARCH_COMPAT : [u32; 1] = [0x40000003];
+#[cfg(target_arch = "aarch64")]
+pub(crate) const AUDIT_ARCH_NATIVE : u32 = 0xC00000B7;
+#[cfg(target_arch = "aarch64")]
+pub(crate) const AUDIT_ARCH_COMPAT : [u32; 1] = [0x40000028];
+
+// The x32 ABI uses the native architecture identifier, but sets this bit in system call numbers
+#[cfg(target_arch = "x86_64")]
+pub(crate) const X32_SYSCALL_BIT : u32 = 0x40000000;
+
+/* @Classic BPF instruction constructors, same as `BPF_STMT` and `BPF_JUMP` C macros */
+pub fn bpf_stmt(code: u32, k: u32) -> sock_filter
+{
+    sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+pub fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter
+{
+    sock_filter { code: code as u16, jt, jf, k }
+}
+/* @/Classic BPF instruction constructors, same as `BPF_STMT` and `BPF_JUMP` C macros */
+
+/*
+ * Loads a raw BPF program into the kernel using `seccomp` system call. The filter is
+ * stacked on top of all filters already installed for the current process. Returns
+ * a file descriptor of the notifications listener if `flags` requested it.
+ */
+pub fn load_filter(filter: &[sock_filter], flags: c_ulong) -> c_int
+{
+    let filter_prog = sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_ptr() as *mut sock_filter
+    };
+
+    let result = unsafe {
+        libc::syscall(libc::SYS_seccomp, SECCOMP_SET_MODE_FILTER, flags, &filter_prog as *const sock_fprog)
+    };
+
+    if result == SYS_EXEC_FAILED as libc::c_long
+    { crate::helper_functions::panic_on_syscall!("seccomp"); }
+
+    result as c_int
+}
+
+/*
+ * Builds a filter that kills a process on any system call made using an ABI other than
+ * the native one: 32-bit `int 0x80` calls and x32 calls on x86_64, AArch32 calls on
+ * AArch64. Compatibility ABIs are let through only if the caller explicitly allows them.
+ */
+pub fn get_arch_filter(allow_compat: bool) -> Vec<sock_filter>
+{
+    let compat_action = if allow_compat { libc::SECCOMP_RET_ALLOW } else { libc::SECCOMP_RET_KILL_PROCESS };
+
+    // Checks applied to system calls of the native architecture
+    let mut native_filter : Vec<sock_filter> = vec![];
+
+    #[cfg(target_arch = "x86_64")]
+    native_filter.extend([
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_NR_OFFSET),
+        bpf_jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, X32_SYSCALL_BIT, 0, 1),
+        bpf_stmt(libc::BPF_RET | libc::BPF_K, compat_action)
+    ]);
+
+    native_filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+
+    let mut filter : Vec<sock_filter> = vec![
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARCH_OFFSET),
+        bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH_NATIVE, 0, native_filter.len() as u8)
+    ];
+
+    filter.extend(native_filter);
+
+    for arch in AUDIT_ARCH_COMPAT {
+        filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, arch, 0, 1));
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, compat_action));
+    }
+
+    filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS));
+    filter
+}
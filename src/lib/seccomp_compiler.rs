@@ -0,0 +1,378 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+use libc::{c_long, sock_filter};
+use crate::seccomp_bpf::{bpf_jump, bpf_stmt, AUDIT_ARCH_NATIVE, SECCOMP_DATA_ARCH_OFFSET, SECCOMP_DATA_ARGS_OFFSET, SECCOMP_DATA_NR_OFFSET};
+use crate::syscall_tables::{SyscallTable, COMPAT_TABLES, NATIVE_TABLE};
+
+/*
+ * A pure-Rust replacement of the libseccomp-based SECCOMP context (see `seccomp_context`),
+ * enabled by the `native-bpf` feature. It provides the same interface and compiles rules
+ * to a classic BPF program itself, using system calls tables of supported architectures.
+ */
+
+pub const SCMP_ACT_ALLOW : u32 = libc::SECCOMP_RET_ALLOW;
+pub const SCMP_ACT_KILL_PROCESS : u32 = libc::SECCOMP_RET_KILL_PROCESS;
+
+#[allow(non_snake_case)]
+pub fn SCMP_ACT_ERRNO(errno: u32) -> u32
+{
+    libc::SECCOMP_RET_ERRNO | (errno & libc::SECCOMP_RET_DATA)
+}
+
+// Checks whether `(arg & mask) == value` for a 64-bit system call argument
+#[derive(Debug, Clone, Copy)]
+pub struct ArgComparator
+{
+    arg   : u32,
+    mask  : u64,
+    value : u64
+}
+
+struct ScmpRule
+{
+    action      : u32,
+    comparators : Vec<ArgComparator>
+}
+
+pub struct ScmpContext
+{
+    default_action : u32,
+    compat_enabled : bool,
+    rules          : BTreeMap<c_long, Vec<ScmpRule>> // Rules by native system call numbers
+}
+
+impl ScmpContext
+{
+    pub fn init_with_action(default_action: u32) -> Result<Self, String>
+    {
+        Ok(Self { default_action, compat_enabled: false, rules: BTreeMap::new() })
+    }
+
+    /* @Let rules of the filter apply to compatibility ABIs too */
+    pub fn add_compat_arches(&mut self) -> Result<(), String>
+    {
+        self.compat_enabled = true;
+        Ok(())
+    }
+    /* @/Let rules of the filter apply to compatibility ABIs too */
+
+    pub fn add_rule(&mut self, action: u32, sys_call: c_long, comparators: &[ArgComparator]) -> Result<(), String>
+    {
+        if NATIVE_TABLE.get_name(sys_call).is_none()
+        { return Err(format!("unknown system call {}", sys_call)); }
+
+        if comparators.iter().any(|comparator| comparator.arg >= 6)
+        { return Err(format!("invalid argument index for system call {}", sys_call)); }
+
+        self.rules.entry(sys_call).or_default().push(ScmpRule { action, comparators: comparators.to_vec() });
+        Ok(())
+    }
+
+    /* @Generate a BPF program, equal to the filter */
+    pub fn export_bpf(&self) -> Result<Vec<sock_filter>, String>
+    {
+        let mut arch_tables : Vec<&SyscallTable> = vec![&NATIVE_TABLE];
+        if self.compat_enabled { arch_tables.extend(COMPAT_TABLES.iter().filter(|table| !table.x32_abi)); }
+
+        let mut filter : Vec<sock_filter> = vec![
+            bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARCH_OFFSET)
+        ];
+
+        for table in arch_tables {
+            let mut arch_filter : Vec<sock_filter> = vec![
+                bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_NR_OFFSET)
+            ];
+
+            if table.audit_arch == AUDIT_ARCH_NATIVE { arch_filter.extend(self.compile_x32_syscalls()?); }
+            arch_filter.extend(self.compile_syscalls(table)?);
+
+            filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, table.audit_arch, 1, 0));
+            filter.push(bpf_stmt(libc::BPF_JMP | libc::BPF_JA, arch_filter.len() as u32));
+            filter.extend(arch_filter);
+        }
+
+        // System calls of architectures missing in the filter kill the whole process
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, SCMP_ACT_KILL_PROCESS));
+
+        if filter.len() > libc::BPF_MAXINSNS as usize
+        { return Err(format!("BPF program is too long ({} instructions)", filter.len())); }
+
+        Ok(filter)
+    }
+    /* @/Generate a BPF program, equal to the filter */
+
+    /*
+     * On x86-64, calls of the x32 ABI have the native architecture identifier, so they're
+     * dispatched by the number, loaded into the accumulator. If the ABI is not enabled,
+     * they kill the process, like calls of other architectures missing in the filter.
+     */
+    #[cfg(target_arch = "x86_64")]
+    fn compile_x32_syscalls(&self) -> Result<Vec<sock_filter>, String>
+    {
+        let x32_filter = match COMPAT_TABLES.iter().find(|table| table.x32_abi && self.compat_enabled) {
+            Some(table) => self.compile_syscalls(table)?,
+            None => vec![bpf_stmt(libc::BPF_RET | libc::BPF_K, SCMP_ACT_KILL_PROCESS)]
+        };
+
+        let mut filter : Vec<sock_filter> = vec![
+            bpf_jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, crate::seccomp_bpf::X32_SYSCALL_BIT, 1, 0),
+            bpf_stmt(libc::BPF_JMP | libc::BPF_JA, x32_filter.len() as u32)
+        ];
+
+        filter.extend(x32_filter);
+        Ok(filter)
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fn compile_x32_syscalls(&self) -> Result<Vec<sock_filter>, String>
+    {
+        Ok(vec![])
+    }
+
+    // Expects the system call number in the accumulator
+    fn compile_syscalls(&self, table: &SyscallTable) -> Result<Vec<sock_filter>, String>
+    {
+        let mut filter : Vec<sock_filter> = vec![];
+
+        for (sys_call, rules) in &self.rules {
+            // Rules are added by native numbers, so we translate them using names
+            let sys_name = NATIVE_TABLE.get_name(*sys_call).unwrap();
+            let table_sys_call = match table.get_number(sys_name) {
+                Some(table_sys_call) => table_sys_call,
+                None => continue
+            };
+
+            let rules_filter = self.compile_rules(rules)?;
+
+            filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, table_sys_call as u32, 1, 0));
+            filter.push(bpf_stmt(libc::BPF_JMP | libc::BPF_JA, rules_filter.len() as u32));
+            filter.extend(rules_filter);
+        }
+
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, self.default_action));
+        Ok(filter)
+    }
+
+    /*
+     * Rules of a single system call are checked from the most restrictive action to the least
+     * restrictive one, the same way the kernel picks an action from results of several filters.
+     * Each comparison of a 64-bit argument is done in two steps, for its low and high words.
+     */
+    fn compile_rules(&self, rules: &[ScmpRule]) -> Result<Vec<sock_filter>, String>
+    {
+        let mut rules_sorted : Vec<&ScmpRule> = rules.iter().collect();
+        rules_sorted.sort_by_key(|rule| get_action_priority(rule.action));
+
+        let mut filter : Vec<sock_filter> = vec![];
+
+        'rules: for rule in rules_sorted {
+            let mut rule_filter : Vec<sock_filter> = vec![];
+            let mut fail_jumps : Vec<usize> = vec![];
+
+            for comparator in &rule.comparators {
+                // Arguments are stored in little-endian order on all supported architectures
+                let words = [
+                    (0, comparator.mask as u32, comparator.value as u32),
+                    (4, (comparator.mask >> 32) as u32, (comparator.value >> 32) as u32)
+                ];
+
+                for (word_offset, mask, value) in words {
+                    // Value has bits outside of the mask, so the rule never matches
+                    if value & !mask != 0 { continue 'rules; }
+                    if mask == 0 { continue; }
+
+                    let arg_offset = SECCOMP_DATA_ARGS_OFFSET + comparator.arg * 8 + word_offset;
+                    rule_filter.push(bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, arg_offset));
+
+                    if mask != u32::MAX
+                    { rule_filter.push(bpf_stmt(libc::BPF_ALU | libc::BPF_AND | libc::BPF_K, mask)); }
+
+                    fail_jumps.push(rule_filter.len());
+                    rule_filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, value, 0, 0));
+                }
+            }
+
+            rule_filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, rule.action));
+
+            // Failed comparisons jump to the next rule
+            for fail_jump in fail_jumps {
+                let fail_offset = rule_filter.len() - fail_jump - 1;
+
+                rule_filter[fail_jump].jf = match u8::try_from(fail_offset) {
+                    Ok(fail_offset) => fail_offset,
+                    Err(_) => return Err("too many argument comparators in a single rule".to_string())
+                };
+            }
+
+            filter.extend(rule_filter);
+
+            // Rules after an unconditional one are never reached
+            if rule.comparators.is_empty() { return Ok(filter); }
+        }
+
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, self.default_action));
+        Ok(filter)
+    }
+}
+
+// The kernel treats lower signed values of actions as more restrictive ones
+fn get_action_priority(action: u32) -> i32
+{
+    (action & libc::SECCOMP_RET_ACTION_FULL) as i32
+}
+
+/* @Comparators of system call arguments */
+pub fn arg_equal(arg: u32, value: u64) -> ArgComparator
+{
+    ArgComparator { arg, mask: u64::MAX, value }
+}
+
+pub fn arg_masked_equal(arg: u32, mask: u64, value: u64) -> ArgComparator
+{
+    ArgComparator { arg, mask, value }
+}
+/* @/Comparators of system call arguments */
+
+/* @Resolution of system call names of the native architecture */
+pub fn get_syscall_name(sys_call: c_long) -> Option<String>
+{
+    NATIVE_TABLE.get_name(sys_call).map(str::to_owned)
+}
+
+pub fn get_syscall_number(sys_name: &str) -> Option<c_long>
+{
+    NATIVE_TABLE.get_number(sys_name)
+}
+/* @/Resolution of system call names of the native architecture */
+
+#[cfg(test)]
+mod tests
+{
+    use libc::sock_filter;
+    use crate::seccomp_bpf::AUDIT_ARCH_NATIVE;
+    use crate::syscall_tables::COMPAT_TABLES;
+    use super::{arg_equal, arg_masked_equal, ScmpContext, SCMP_ACT_ALLOW, SCMP_ACT_ERRNO, SCMP_ACT_KILL_PROCESS};
+
+    /* Runs a BPF program on `struct seccomp_data`, supporting instructions the compiler emits */
+    fn run_filter(filter: &[sock_filter], arch: u32, sys_call: u32, args: [u64; 6]) -> u32
+    {
+        let load_word = |offset: u32| match offset {
+            0 => sys_call,
+            4 => arch,
+            16..=63 => {
+                let arg = args[(offset as usize - 16) / 8];
+                if offset.is_multiple_of(8) { arg as u32 } else { (arg >> 32) as u32 }
+            },
+            _ => panic!("Invalid offset {} of a load instruction", offset)
+        };
+
+        let (mut accumulator, mut pc) = (0u32, 0usize);
+
+        loop {
+            let instruction = filter[pc];
+            pc += 1;
+
+            match instruction.code as u32 {
+                code if code == libc::BPF_LD | libc::BPF_W | libc::BPF_ABS => accumulator = load_word(instruction.k),
+                code if code == libc::BPF_ALU | libc::BPF_AND | libc::BPF_K => accumulator &= instruction.k,
+                code if code == libc::BPF_JMP | libc::BPF_JA => pc += instruction.k as usize,
+                code if code == libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K =>
+                    pc += if accumulator == instruction.k { instruction.jt } else { instruction.jf } as usize,
+                code if code == libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K =>
+                    pc += if accumulator >= instruction.k { instruction.jt } else { instruction.jf } as usize,
+                code if code == libc::BPF_RET | libc::BPF_K => return instruction.k,
+                code => panic!("Unexpected BPF instruction {:#x}", code)
+            }
+        }
+    }
+
+    fn run_native(filter: &[sock_filter], sys_call: libc::c_long, args: [u64; 6]) -> u32
+    { run_filter(filter, AUDIT_ARCH_NATIVE, sys_call as u32, args) }
+
+    #[test]
+    fn filter_applies_rules_and_default_action()
+    {
+        let mut ctx = ScmpContext::init_with_action(SCMP_ACT_ALLOW).unwrap();
+        ctx.add_rule(SCMP_ACT_KILL_PROCESS, libc::SYS_ptrace, &[]).unwrap();
+        ctx.add_rule(SCMP_ACT_ERRNO(libc::EPERM as u32), libc::SYS_socket, &[arg_equal(0, libc::AF_INET as u64)]).unwrap();
+
+        let filter = ctx.export_bpf().unwrap();
+
+        assert_eq!(run_native(&filter, libc::SYS_getpid, [0; 6]), SCMP_ACT_ALLOW);
+        assert_eq!(run_native(&filter, libc::SYS_ptrace, [0; 6]), SCMP_ACT_KILL_PROCESS);
+        assert_eq!(run_native(&filter, libc::SYS_socket, [libc::AF_INET as u64, 0, 0, 0, 0, 0]), SCMP_ACT_ERRNO(libc::EPERM as u32));
+        assert_eq!(run_native(&filter, libc::SYS_socket, [libc::AF_UNIX as u64, 0, 0, 0, 0, 0]), SCMP_ACT_ALLOW);
+
+        // High word of an argument is compared too
+        assert_eq!(run_native(&filter, libc::SYS_socket, [libc::AF_INET as u64 | 1 << 32, 0, 0, 0, 0, 0]), SCMP_ACT_ALLOW);
+    }
+
+    #[test]
+    fn filter_prefers_restrictive_rules()
+    {
+        let mut ctx = ScmpContext::init_with_action(SCMP_ACT_KILL_PROCESS).unwrap();
+        ctx.add_rule(SCMP_ACT_ALLOW, libc::SYS_clone, &[]).unwrap();
+        ctx.add_rule(SCMP_ACT_ERRNO(libc::EPERM as u32), libc::SYS_clone, &[arg_masked_equal(0, 0xff00, 0x1100)]).unwrap();
+        ctx.add_rule(SCMP_ACT_KILL_PROCESS, libc::SYS_clone, &[arg_masked_equal(0, libc::CLONE_NEWUSER as u64, libc::CLONE_NEWUSER as u64)]).unwrap();
+
+        // Comparator with a value outside of its mask never matches
+        ctx.add_rule(SCMP_ACT_KILL_PROCESS, libc::SYS_clone, &[arg_masked_equal(1, 0xff, 0x100)]).unwrap();
+
+        let filter = ctx.export_bpf().unwrap();
+        let clone_args = |flags: u64| [flags, 0x100, 0, 0, 0, 0];
+
+        assert_eq!(run_native(&filter, libc::SYS_clone, clone_args(libc::SIGCHLD as u64)), SCMP_ACT_ALLOW);
+        assert_eq!(run_native(&filter, libc::SYS_clone, clone_args(0x11ff)), SCMP_ACT_ERRNO(libc::EPERM as u32));
+        assert_eq!(run_native(&filter, libc::SYS_clone, clone_args(0x1100 | libc::CLONE_NEWUSER as u64)), SCMP_ACT_KILL_PROCESS);
+        assert_eq!(run_native(&filter, libc::SYS_getpid, [0; 6]), SCMP_ACT_KILL_PROCESS);
+    }
+
+    #[test]
+    fn filter_checks_architectures()
+    {
+        let mut ctx = ScmpContext::init_with_action(SCMP_ACT_ERRNO(libc::ENOSYS as u32)).unwrap();
+        ctx.add_rule(SCMP_ACT_ALLOW, libc::SYS_getpid, &[]).unwrap();
+
+        // Calls of compatibility ABIs kill the process, unless they are enabled
+        let filter = ctx.export_bpf().unwrap();
+        for table in &COMPAT_TABLES
+        { assert_eq!(run_filter(&filter, table.audit_arch, table.get_number("getpid").unwrap() as u32, [0; 6]), SCMP_ACT_KILL_PROCESS); }
+
+        ctx.add_compat_arches().unwrap();
+        let filter = ctx.export_bpf().unwrap();
+
+        for table in &COMPAT_TABLES {
+            assert_eq!(run_filter(&filter, table.audit_arch, table.get_number("getpid").unwrap() as u32, [0; 6]), SCMP_ACT_ALLOW);
+            assert_eq!(run_filter(&filter, table.audit_arch, table.get_number("getppid").unwrap() as u32, [0; 6]), SCMP_ACT_ERRNO(libc::ENOSYS as u32));
+        }
+
+        assert_eq!(run_native(&filter, libc::SYS_getpid, [0; 6]), SCMP_ACT_ALLOW);
+        assert_eq!(run_filter(&filter, 0x12345678, libc::SYS_getpid as u32, [0; 6]), SCMP_ACT_KILL_PROCESS);
+    }
+
+    #[test]
+    fn rules_are_validated()
+    {
+        let mut ctx = ScmpContext::init_with_action(SCMP_ACT_ALLOW).unwrap();
+
+        assert!(ctx.add_rule(SCMP_ACT_KILL_PROCESS, 100000, &[]).is_err());
+        assert!(ctx.add_rule(SCMP_ACT_KILL_PROCESS, libc::SYS_socket, &[arg_equal(6, 0)]).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::io::FromRawFd;
+use libc::{c_char, c_int, c_long, sock_filter};
+use seccomp_sys::*;
+use crate::SYS_EXEC_FAILED;
+
+pub use seccomp_sys::{scmp_arg_cmp, SCMP_ACT_ALLOW, SCMP_ACT_ERRNO, SCMP_ACT_KILL_PROCESS};
+
+#[link(name = "seccomp")]
+extern "C" {
+    fn seccomp_syscall_resolve_num_arch(arch_token: u32, num: c_int) -> *mut c_char;
+}
+
+/*
+ * A thin wrapper around a libseccomp filter context. We don't load filters using
+ * libseccomp, but export them as BPF programs instead, so several filters can be
+ * stacked without libseccomp calling system calls forbidden by previous ones.
+ */
+pub struct ScmpContext
+{
+    ctx : *mut scmp_filter_ctx
+}
+
+// Compatibility ABIs of the native architecture, allowed only on request
+#[cfg(target_arch = "x86_64")]
+const SCMP_ARCH_COMPAT : [scmp_arch; 2] = [scmp_arch::SCMP_ARCH_X86, scmp_arch::SCMP_ARCH_X32];
+#[cfg(target_arch = "aarch64")]
+const SCMP_ARCH_COMPAT : [scmp_arch; 1] = [scmp_arch::SCMP_ARCH_ARM];
+
+impl ScmpContext
+{
+    pub fn init_with_action(default_action: u32) -> Result<Self, String>
+    {
+        let ctx = unsafe { seccomp_init(default_action) };
+        if ctx.is_null() { return Err("seccomp_init returned null".to_string()); }
+
+        let scmp_ctx = Self { ctx };
+
+        // System calls of architectures missing in the filter kill the whole process
+        let ret = unsafe { seccomp_attr_set(ctx, scmp_filter_attr::SCMP_FLTATR_ACT_BADARCH, SCMP_ACT_KILL_PROCESS) };
+        check_result(ret, "seccomp_attr_set")?;
+
+        Ok(scmp_ctx)
+    }
+
+    /* @Let rules of the filter apply to compatibility ABIs too */
+    pub fn add_compat_arches(&mut self) -> Result<(), String>
+    {
+        for arch in SCMP_ARCH_COMPAT {
+            let ret = unsafe { seccomp_arch_add(self.ctx, arch as u32) };
+            check_result(ret, "seccomp_arch_add")?;
+        }
+
+        Ok(())
+    }
+    /* @/Let rules of the filter apply to compatibility ABIs too */
+
+    pub fn add_rule(&mut self, action: u32, sys_call: c_long, comparators: &[scmp_arg_cmp]) -> Result<(), String>
+    {
+        // Native system call number is translated for every architecture of the filter
+        let ret = unsafe { seccomp_rule_add_array(self.ctx, action, sys_call as c_int,
+                                                  comparators.len() as u32, comparators.as_ptr()) };
+        check_result(ret, "seccomp_rule_add_array")
+    }
+
+    /* @Generate a BPF program, equal to the filter */
+    pub fn export_bpf(&self) -> Result<Vec<sock_filter>, String>
+    {
+        let memfd_name = CString::new("limtrac-bpf").unwrap();
+        let memfd = unsafe { libc::memfd_create(memfd_name.as_ptr(), libc::MFD_CLOEXEC) };
+        if memfd == SYS_EXEC_FAILED { return Err(format!("memfd_create failed with errno {}", nix::errno::errno())); }
+
+        let mut memfd_file = unsafe { File::from_raw_fd(memfd) };
+
+        let ret = unsafe { seccomp_export_bpf(self.ctx, memfd) };
+        check_result(ret, "seccomp_export_bpf")?;
+
+        let mut program : Vec<u8> = vec![];
+        memfd_file.seek(SeekFrom::Start(0))
+            .and_then(|_| memfd_file.read_to_end(&mut program))
+            .map_err(|err| format!("Cannot read exported BPF program: {}", err))?;
+
+        // Exported instructions have the same layout as `struct sock_filter`
+        Ok(program.chunks_exact(std::mem::size_of::<sock_filter>())
+            .map(|insn| sock_filter {
+                code: u16::from_ne_bytes([insn[0], insn[1]]),
+                jt: insn[2],
+                jf: insn[3],
+                k: u32::from_ne_bytes([insn[4], insn[5], insn[6], insn[7]])
+            })
+            .collect())
+    }
+    /* @/Generate a BPF program, equal to the filter */
+}
+
+impl Drop for ScmpContext
+{
+    fn drop(&mut self)
+    { unsafe { seccomp_release(self.ctx) }; }
+}
+
+fn check_result(ret: c_int, function_name: &str) -> Result<(), String>
+{
+    if ret != 0 { Err(format!("{} returned error {}", function_name, ret)) }
+    else { Ok(()) }
+}
+
+/* @Comparators of system call arguments */
+pub fn arg_equal(arg: u32, value: u64) -> scmp_arg_cmp
+{
+    scmp_arg_cmp { arg, op: scmp_compare::SCMP_CMP_EQ, datum_a: value, datum_b: 0 }
+}
+
+pub fn arg_masked_equal(arg: u32, mask: u64, value: u64) -> scmp_arg_cmp
+{
+    scmp_arg_cmp { arg, op: scmp_compare::SCMP_CMP_MASKED_EQ, datum_a: mask, datum_b: value }
+}
+/* @/Comparators of system call arguments */
+
+/* @Resolution of system call names of the native architecture */
+pub fn get_syscall_name(sys_call: c_long) -> Option<String>
+{
+    let name_ptr = unsafe { seccomp_syscall_resolve_num_arch(scmp_arch::SCMP_ARCH_NATIVE as u32, sys_call as c_int) };
+    if name_ptr.is_null() { return None; }
+
+    // Resolved name is allocated by libseccomp and must be freed by a caller
+    let name = unsafe { CStr::from_ptr(name_ptr) }.to_str().ok().map(str::to_owned);
+    unsafe { libc::free(name_ptr as *mut libc::c_void) };
+
+    name
+}
+
+pub fn get_syscall_number(sys_name: &str) -> Option<c_long>
+{
+    let sys_name = CString::new(sys_name).ok()?;
+    let sys_call = unsafe { seccomp_syscall_resolve_name(sys_name.as_ptr()) };
+
+    // Negative numbers are either errors or pseudo system calls, missing on this architecture
+    if sys_call < 0 { None } else { Some(sys_call as c_long) }
+}
+/* @/Resolution of system call names of the native architecture */
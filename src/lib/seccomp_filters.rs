@@ -0,0 +1,85 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::sock_filter;
+use crate::ExecProgGuard;
+use crate::seccomp_context::{ScmpContext, SCMP_ACT_ALLOW, SCMP_ACT_KILL_PROCESS};
+
+/*
+ * SECCOMP filters of a single execution request, compiled to BPF programs in the parent
+ * process before `fork`, so the child process only needs to load them. Filters are loaded
+ * in the order of fields, the notifications filter (if any) is loaded after the first one.
+ */
+pub struct ScmpFilters
+{
+    pub arch_filter    : Vec<sock_filter>,         // Kills processes using foreign ABIs
+    pub deny_filter    : Option<Vec<sock_filter>>, // Denylist of the requested tier
+    pub profile_filter : Option<Vec<sock_filter>>  // Allowlist from the requested profile
+}
+
+impl ScmpFilters
+{
+    pub fn build(exec_prog_guard : &ExecProgGuard) -> Self
+    {
+        // Kill processes that use ABIs other than the native one (unless explicitly allowed),
+        // as system call numbers differ between them and other filters check native ones.
+        let arch_filter = crate::seccomp_bpf::get_arch_filter(exec_prog_guard.scmp_allow_compat);
+
+        // Prevent process from using unwanted system calls of the requested tier
+        let deny_filter = exec_prog_guard.scmp_deny_common.then(|| {
+            let mut ctx = init_scmp_context(SCMP_ACT_ALLOW, exec_prog_guard);
+            crate::seccomp_tiers::apply_deny_rules(&mut ctx, exec_prog_guard.scmp_deny_tier);
+            export_scmp_context(&ctx)
+        });
+
+        // Allow only system calls listed in the profile. Denylist is a separate filter, which
+        // takes precedence, so calls denied by it can't be allowed by the profile.
+        let profile_filter = crate::seccomp_profile::load_requested_profile(exec_prog_guard.scmp_profile_path)
+            .map(|scmp_profile| {
+                let mut ctx = init_scmp_context(SCMP_ACT_KILL_PROCESS, exec_prog_guard);
+                scmp_profile.apply(&mut ctx);
+                export_scmp_context(&ctx)
+            });
+
+        Self { arch_filter, deny_filter, profile_filter }
+    }
+}
+
+fn init_scmp_context(default_action : u32, exec_prog_guard : &ExecProgGuard) -> ScmpContext
+{
+    let mut ctx = match ScmpContext::init_with_action(default_action) {
+        Ok(ctx) => ctx,
+        Err(err) => { panic!("Cannot initialize SECCOMP context: {}", err) }
+    };
+
+    // Apply the same rules to compatibility ABIs, if they are allowed
+    if exec_prog_guard.scmp_allow_compat {
+        if let Err(err) = ctx.add_compat_arches()
+        { panic!("Cannot add compatibility ABIs to SECCOMP context: {}", err) }
+    }
+
+    ctx
+}
+
+fn export_scmp_context(ctx : &ScmpContext) -> Vec<sock_filter>
+{
+    match ctx.export_bpf() {
+        Ok(bpf_program) => bpf_program,
+        Err(err) => { panic!("Cannot compile SECCOMP policy: {}", err) }
+    }
+}
@@ -0,0 +1,601 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{IoSlice, IoSliceMut};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::os::unix::io::RawFd;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+use libc::{c_int, c_long, c_ulong, c_ulonglong, sock_filter};
+use nix::sys::socket::{ControlMessage, ControlMessageOwned, MsgFlags};
+use crate::{ExecProgGuard, SYS_EXEC_FAILED};
+use crate::seccomp_bpf::{bpf_jump, bpf_stmt, AUDIT_ARCH_NATIVE, SECCOMP_DATA_ARCH_OFFSET, SECCOMP_DATA_ARGS_OFFSET,
+                         SECCOMP_DATA_NR_OFFSET, SECCOMP_FILTER_FLAG_NEW_LISTENER, SECCOMP_RET_USER_NOTIF};
+use crate::seccomp_profile::SyscallProfile;
+
+/*
+ * Kernel ABI of SECCOMP user-space notifications, see `linux/seccomp.h`.
+ * Request codes are calculated the same way as `_IOWR` and `_IOW` macros do.
+ */
+
+#[repr(C)]
+struct SeccompData
+{
+    nr : c_int,
+    arch : u32,
+    instruction_pointer : u64,
+    args : [u64; 6]
+}
+
+#[repr(C)]
+struct SeccompNotif
+{
+    id : u64,
+    pid : u32,
+    flags : u32,
+    data : SeccompData
+}
+
+#[repr(C)]
+struct SeccompNotifResp
+{
+    id : u64,
+    val : i64,
+    error : i32,
+    flags : u32
+}
+
+const SECCOMP_IOCTL_NOTIF_RECV : c_ulong = 0xC0502100;
+const SECCOMP_IOCTL_NOTIF_SEND : c_ulong = 0xC0182101;
+const SECCOMP_IOCTL_NOTIF_ID_VALID : c_ulong = 0x40082102;
+const SECCOMP_USER_NOTIF_FLAG_CONTINUE : u32 = 1;
+
+// Strings are read from the memory of a traced process in chunks that never cross a page
+const MEM_READ_CHUNK : u64 = 4096;
+
+/* @System calls whose path arguments are checked by the supervisor */
+fn get_path_syscalls() -> Vec<c_long>
+{
+    let mut syscalls_list = vec![
+        libc::SYS_openat, libc::SYS_openat2,
+        libc::SYS_execve, libc::SYS_execveat,
+        libc::SYS_connect
+    ];
+
+    // Legacy system calls, not available on newer platforms
+    #[cfg(target_arch = "x86_64")]
+    syscalls_list.extend([libc::SYS_open, libc::SYS_creat]);
+
+    syscalls_list
+}
+/* @/System calls whose path arguments are checked by the supervisor */
+
+/*
+ * [PARENT] Builds a filter that turns system calls into notifications before `fork`.
+ *
+ * In learning mode every system call is reported, except ones we need to pass
+ * the listener, as the supervisor can't answer anything until it receives it.
+ * Otherwise, only calls needed by path-based policy and exec limiting are reported.
+ */
+fn get_listener_filter(exec_prog_guard : &ExecProgGuard, child_sock : RawFd) -> Vec<sock_filter>
+{
+    let mut filter : Vec<sock_filter> = vec![
+        // System calls of foreign architectures are handled by other filters
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARCH_OFFSET),
+        bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH_NATIVE, 1, 0),
+        bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW),
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_NR_OFFSET)
+    ];
+
+    if exec_prog_guard.scmp_learn_mode {
+        filter.extend([
+            bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::SYS_close as u32, 0, 1),
+            bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW),
+            // Only `sendmsg` on the channel socket is allowed without a notification
+            bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, libc::SYS_sendmsg as u32, 0, 3),
+            bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARGS_OFFSET),
+            bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, child_sock as u32, 0, 1),
+            bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW),
+            bpf_stmt(libc::BPF_RET | libc::BPF_K, SECCOMP_RET_USER_NOTIF)
+        ]);
+    }
+    else {
+        let mut syscalls_list : Vec<c_long> = vec![];
+
+        if exec_prog_guard.scmp_path_policy { syscalls_list.extend(get_path_syscalls()); }
+        if exec_prog_guard.scmp_deny_exec { syscalls_list.extend([libc::SYS_execve, libc::SYS_execveat]); }
+
+        syscalls_list.sort_unstable();
+        syscalls_list.dedup();
+
+        for sys_call in syscalls_list {
+            filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, sys_call as u32, 0, 1));
+            filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, SECCOMP_RET_USER_NOTIF));
+        }
+
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+    }
+
+    filter
+}
+
+/*
+ * A pair of connected sockets, created before `fork` so the child process can pass
+ * the file descriptor of the notifications listener to the parent process. Listener
+ * appears only when the filter gets loaded, so this can't be done by inheritance.
+ */
+pub struct NotifyChannel
+{
+    parent_sock : RawFd,
+    child_sock : RawFd,
+    listener_filter : Vec<sock_filter>
+}
+
+impl NotifyChannel
+{
+    pub fn new(exec_prog_guard : &ExecProgGuard, fd_min : c_int) -> Self
+    {
+        let mut sockets : [c_int; 2] = [-1, -1];
+
+        if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC,
+                                     0, sockets.as_mut_ptr()) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("socketpair"); }
+
+        let parent_sock = crate::helper_functions::move_fd_above(sockets[0], fd_min);
+        let child_sock = crate::helper_functions::move_fd_above(sockets[1], fd_min);
+
+        let listener_filter = get_listener_filter(exec_prog_guard, child_sock);
+        Self { parent_sock, child_sock, listener_filter }
+    }
+
+    /*
+     * [CHILD] Loads the filter that turns system calls into notifications and passes the
+     * listener to the parent process. Listener must not stay open in the child process,
+     * otherwise the program could answer its own notifications.
+     */
+    pub fn install_listener(&self)
+    {
+        unsafe { libc::close(self.parent_sock) };
+
+        let notify_fd = crate::seccomp_bpf::load_filter(&self.listener_filter, SECCOMP_FILTER_FLAG_NEW_LISTENER);
+
+        let message = [IoSlice::new(&[0u8])];
+        let control = [ControlMessage::ScmRights(&[notify_fd])];
+
+        if nix::sys::socket::sendmsg::<()>(self.child_sock, &message, &control, MsgFlags::empty(), None).is_err()
+        { crate::helper_functions::panic_on_syscall!("sendmsg"); }
+
+        unsafe {
+            libc::close(notify_fd);
+            libc::close(self.child_sock);
+        }
+    }
+
+    /*
+     * [PARENT] Waits for the listener sent by the child process. Returns `None` if
+     * the child process exited before it installed the filter (for example, panicked).
+     */
+    pub fn accept_listener(self, exec_prog_guard : &ExecProgGuard, allow_list: Option<Vec<PathBuf>>, child_pid : libc::pid_t) -> Option<NotifySupervisor>
+    {
+        unsafe { libc::close(self.child_sock) };
+
+        let mut message_buf = [0u8; 1];
+        let mut message = [IoSliceMut::new(&mut message_buf)];
+        let mut control_buf = nix::cmsg_space!([RawFd; 1]);
+
+        let received = nix::sys::socket::recvmsg::<()>(self.parent_sock, &mut message,
+                                                       Some(&mut control_buf), MsgFlags::MSG_CMSG_CLOEXEC);
+        let mut notify_fd : Option<RawFd> = None;
+
+        if let Ok(received) = received {
+            for control in received.cmsgs() {
+                if let ControlMessageOwned::ScmRights(fds) = control
+                { notify_fd = fds.first().copied(); }
+            }
+        }
+
+        unsafe { libc::close(self.parent_sock) };
+
+        notify_fd.map(|notify_fd| NotifySupervisor {
+            notify_fd, allow_list,
+            profile: if exec_prog_guard.scmp_learn_mode { Some(SyscallProfile::new_learning()) } else { None },
+            deny_exec: exec_prog_guard.scmp_deny_exec,
+            denied_count: 0,
+            target_started: false,
+            child_pid,
+            security_violation: false
+        })
+    }
+}
+
+/*
+ * [PARENT] Answers notifications of the filter installed by `install_listener`, so
+ * path-related system calls are allowed only for paths under one of allowed prefixes.
+ * In learning mode, all system calls made by the program are recorded to a profile.
+ * When exec limiting is enabled, any program execution after the target program has
+ * started is treated as a security violation and kills the process that tried it.
+ *
+ * Paths are resolved the way the program sees them, see `resolve_path_beneath`. Note
+ * that the supervisor inspects arguments before the kernel does, so a program could
+ * change them (or a directory they point into) in between. The policy is intended
+ * to be combined with other sandboxing features, not to be used as the only barrier.
+ */
+pub struct NotifySupervisor
+{
+    notify_fd : RawFd,
+    allow_list : Option<Vec<PathBuf>>,
+    pub profile : Option<SyscallProfile>,
+    deny_exec : bool,
+    pub denied_count : c_ulonglong,
+    target_started : bool,
+    child_pid : libc::pid_t,
+    pub security_violation : bool
+}
+
+impl NotifySupervisor
+{
+    /* @Handle incoming notifications for the specified period of time */
+    pub fn serve(&mut self, timeout: Duration)
+    {
+        let serve_until = Instant::now() + timeout;
+
+        loop {
+            let time_left = serve_until.saturating_duration_since(Instant::now());
+            if time_left.is_zero() { return; }
+
+            let mut poll_fd = libc::pollfd { fd: self.notify_fd, events: libc::POLLIN, revents: 0 };
+            let poll_timeout = time_left.as_millis().max(1) as c_int;
+            let poll_result = unsafe { libc::poll(&mut poll_fd, 1, poll_timeout) };
+
+            if poll_result == SYS_EXEC_FAILED {
+                if nix::errno::errno() == libc::EINTR { continue; }
+                crate::helper_functions::panic_on_syscall!("poll");
+            }
+
+            // All processes using the filter have exited, nothing to wait for
+            if poll_fd.revents & libc::POLLHUP != 0
+            { std::thread::sleep(time_left); return; }
+
+            if poll_fd.revents & libc::POLLIN != 0 { self.handle_notification(); }
+        }
+    }
+    /* @/Handle incoming notifications for the specified period of time */
+
+    fn handle_notification(&mut self)
+    {
+        let mut request : SeccompNotif = unsafe { std::mem::zeroed() };
+
+        // Fails if the process was killed while its system call was pending
+        if unsafe { libc::ioctl(self.notify_fd, SECCOMP_IOCTL_NOTIF_RECV, &mut request) } == SYS_EXEC_FAILED
+        { return; }
+
+        let sys_call = request.data.nr as c_long;
+        let path = if get_path_syscalls().contains(&sys_call) { self.get_requested_path(&request) } else { None };
+
+        // The first execution is made by LIMTRAC itself to start the target program
+        let is_exec = sys_call == libc::SYS_execve || sys_call == libc::SYS_execveat;
+
+        // Its path may be not readable if the child process is not dumpable, but it is trusted
+        let is_allowed = match &self.allow_list {
+            Some(_) if is_exec && !self.target_started => true,
+            Some(allow_list) if get_path_syscalls().contains(&sys_call) =>
+                path.as_ref().is_some_and(|path| is_path_allowed(path, allow_list)),
+            _ => true
+        };
+
+        if is_exec && self.target_started && self.deny_exec {
+            // Kill the group (containing the target program) first, while the process that tried
+            // to execute a program still waits, so its parent can't continue after it is killed
+            crate::sandboxing_features::kill_process_group(self.child_pid);
+            unsafe { libc::kill(request.pid as libc::pid_t, libc::SIGKILL) };
+            self.denied_count += 1;
+            self.security_violation = true;
+            return;
+        }
+
+        // System calls made by LIMTRAC before the program is executed are not recorded
+        if is_exec && !self.target_started { self.target_started = true; }
+
+        if let Some(profile) = self.profile.as_mut() {
+            if self.target_started && is_allowed
+            { profile.record(request.data.nr, &request.data.args, path); }
+        }
+
+        // Ensure that the process we read arguments of still waits for our response
+        if unsafe { libc::ioctl(self.notify_fd, SECCOMP_IOCTL_NOTIF_ID_VALID, &request.id) } == SYS_EXEC_FAILED
+        { return; }
+
+        let mut response = SeccompNotifResp { id: request.id, val: 0, error: 0, flags: 0 };
+
+        if is_allowed { response.flags = SECCOMP_USER_NOTIF_FLAG_CONTINUE; }
+        else {
+            response.error = -libc::EPERM;
+            self.denied_count += 1;
+        }
+
+        unsafe { libc::ioctl(self.notify_fd, SECCOMP_IOCTL_NOTIF_SEND, &response) };
+    }
+
+    /* @Get an absolute path the system call of the traced process refers to */
+    fn get_requested_path(&self, request: &SeccompNotif) -> Option<PathBuf>
+    {
+        let pid = request.pid;
+        let args = &request.data.args;
+        let sys_call = request.data.nr as c_long;
+
+        let (dir_fd, path) = match sys_call {
+            libc::SYS_openat | libc::SYS_openat2 | libc::SYS_execveat =>
+                (args[0] as c_int, read_proc_string(pid, args[1])?),
+            libc::SYS_execve => (libc::AT_FDCWD, read_proc_string(pid, args[0])?),
+            #[cfg(target_arch = "x86_64")]
+            libc::SYS_open | libc::SYS_creat => (libc::AT_FDCWD, read_proc_string(pid, args[0])?),
+            libc::SYS_connect => (libc::AT_FDCWD, read_proc_sockaddr_path(pid, args[1], args[2])?),
+            _ => return None
+        };
+
+        let path = PathBuf::from(std::ffi::OsStr::from_bytes(&path));
+        let root_path = PathBuf::from(format!("/proc/{}/root", pid));
+
+        // Relative paths are resolved against a working directory or a directory descriptor
+        let base_path = if path.is_absolute() { PathBuf::from("/") } else {
+            let base_link = if dir_fd == libc::AT_FDCWD { format!("/proc/{}/cwd", pid) }
+                            else { format!("/proc/{}/fd/{}", pid, dir_fd) };
+
+            // Links show paths as we see them, so the root directory of the process is stripped
+            let root_link = std::fs::read_link(&root_path).ok()?;
+            Path::new("/").join(std::fs::read_link(base_link).ok()?.strip_prefix(root_link).ok()?)
+        };
+
+        resolve_path_beneath(&root_path, &base_path, &path)
+    }
+    /* @/Get an absolute path the system call of the traced process refers to */
+}
+
+impl Drop for NotifySupervisor
+{
+    fn drop(&mut self)
+    { unsafe { libc::close(self.notify_fd) }; }
+}
+
+/* @Read a NUL-terminated string from the memory of a process */
+fn read_proc_string(pid: u32, address: u64) -> Option<Vec<u8>>
+{
+    let proc_mem = File::open(format!("/proc/{}/mem", pid)).ok()?;
+    let mut result : Vec<u8> = vec![];
+    let mut address = address;
+
+    while result.len() <= libc::PATH_MAX as usize {
+        let mut chunk = vec![0u8; (MEM_READ_CHUNK - address % MEM_READ_CHUNK) as usize];
+        let chunk_len = proc_mem.read_at(&mut chunk, address).ok()?;
+        if chunk_len == 0 { return None; }
+
+        if let Some(end) = chunk[..chunk_len].iter().position(|byte| *byte == 0) {
+            result.extend_from_slice(&chunk[..end]);
+            return Some(result);
+        }
+
+        result.extend_from_slice(&chunk[..chunk_len]);
+        address += chunk_len as u64;
+    }
+
+    None
+}
+/* @/Read a NUL-terminated string from the memory of a process */
+
+/* @Read a filesystem path of a Unix socket address from the memory of a process */
+fn read_proc_sockaddr_path(pid: u32, address: u64, length: u64) -> Option<Vec<u8>>
+{
+    let length = (length as usize).min(std::mem::size_of::<libc::sockaddr_un>());
+    let path_offset = std::mem::size_of::<libc::sa_family_t>();
+    if length <= path_offset { return None; }
+
+    let proc_mem = File::open(format!("/proc/{}/mem", pid)).ok()?;
+    let mut sockaddr = vec![0u8; length];
+    proc_mem.read_exact_at(&mut sockaddr, address).ok()?;
+
+    // Only Unix sockets bound to a filesystem path can be checked against a policy,
+    // so connections to other families and abstract namespace are always denied.
+    let family = libc::sa_family_t::from_ne_bytes([sockaddr[0], sockaddr[1]]);
+    if family != libc::AF_UNIX as libc::sa_family_t || sockaddr[path_offset] == 0 { return None; }
+
+    let path = &sockaddr[path_offset..];
+    let path_end = path.iter().position(|byte| *byte == 0).unwrap_or(path.len());
+
+    Some(path[..path_end].to_vec())
+}
+/* @/Read a filesystem path of a Unix socket address from the memory of a process */
+
+/* @Resolve `.` and `..` components of an absolute path without accessing the filesystem */
+pub fn normalize_path(path: &Path) -> PathBuf
+{
+    let mut result = PathBuf::from("/");
+
+    for component in path.components() {
+        match component {
+            Component::ParentDir => { result.pop(); }
+            Component::Normal(name) => result.push(name),
+            _ => { }
+        }
+    }
+
+    result
+}
+/* @/Resolve `.` and `..` components of an absolute path without accessing the filesystem */
+
+/* @Check whether a resolved path is under one of allowed prefixes (compared by components) */
+pub fn is_path_allowed(path: &Path, allow_list: &[PathBuf]) -> bool
+{
+    allow_list.iter().any(|allowed| path.starts_with(allowed))
+}
+/* @/Check whether a resolved path is under one of allowed prefixes (compared by components) */
+
+// Symbolic links followed while resolving a single path, the same limit the kernel has
+const SYMLINKS_MAX : usize = 40;
+
+/*
+ * Resolves a path relative to a base directory the way the kernel does, beneath a root
+ * directory (`/proc/<pid>/root` of the traced process), so `..` after a symbolic link
+ * goes to the parent of its target. Symbolic links are followed only if they are owned
+ * by root (such as `/lib` on merged-usr systems). Others could be planted by the program
+ * to point anywhere, so paths going through them are refused, as well as unreadable ones.
+ * Missing components are resolved as text, the system call fails on them anyway.
+ */
+pub fn resolve_path_beneath(root_path: &Path, base_path: &Path, path: &Path) -> Option<PathBuf>
+{
+    let mut result = normalize_path(base_path);
+    let mut pending_names = get_path_names(path);
+    let mut symlinks_count : usize = 0;
+
+    if path.is_absolute() { result = PathBuf::from("/"); }
+
+    while let Some(name) = pending_names.pop_front() {
+        if name == ".." { result.pop(); continue; }
+        result.push(&name);
+
+        let real_path = root_path.join(result.strip_prefix("/").unwrap());
+        let metadata = match std::fs::symlink_metadata(&real_path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(_) => return None
+        };
+
+        if !metadata.file_type().is_symlink() { continue; }
+
+        symlinks_count += 1;
+        if metadata.uid() != 0 || symlinks_count > SYMLINKS_MAX { return None; }
+
+        // Link is replaced by its target, which is relative to the directory it is in
+        let link_target = std::fs::read_link(&real_path).ok()?;
+        result.pop();
+
+        if link_target.is_absolute() { result = PathBuf::from("/"); }
+        for name in get_path_names(&link_target).into_iter().rev() { pending_names.push_front(name); }
+    }
+
+    Some(result)
+}
+
+/* Splits a path into names of its components, keeping `..` to be resolved later */
+fn get_path_names(path: &Path) -> VecDeque<OsString>
+{
+    path.components()
+        .filter_map(|component| match component {
+            Component::ParentDir => Some(OsString::from("..")),
+            Component::Normal(name) => Some(name.to_os_string()),
+            _ => None
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::os::unix::fs::{lchown, symlink};
+    use std::path::{Path, PathBuf};
+    use super::{is_path_allowed, normalize_path, resolve_path_beneath};
+
+    /* Creates a fake root with `/work` and `/etc/shadow`, and a link owned by the given user */
+    fn make_root(name: &str, link_owner: u32) -> PathBuf
+    {
+        let root_path = std::env::temp_dir().join(format!("limtrac-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root_path);
+
+        std::fs::create_dir_all(root_path.join("work")).unwrap();
+        std::fs::create_dir_all(root_path.join("etc")).unwrap();
+        std::fs::write(root_path.join("etc/shadow"), "").unwrap();
+
+        symlink("/etc", root_path.join("work/link")).unwrap();
+        lchown(root_path.join("work/link"), Some(link_owner), Some(link_owner)).unwrap();
+
+        root_path
+    }
+
+    fn is_root() -> bool
+    { unsafe { libc::geteuid() == 0 } }
+
+    #[test]
+    fn normalize_path_folds_dots()
+    {
+        assert_eq!(normalize_path(Path::new("/tmp/./work/../etc/passwd")), PathBuf::from("/tmp/etc/passwd"));
+        assert_eq!(normalize_path(Path::new("/../../etc")), PathBuf::from("/etc"));
+    }
+
+    #[test]
+    fn is_path_allowed_compares_components()
+    {
+        let allow_list = [PathBuf::from("/tmp/work")];
+
+        assert!(is_path_allowed(Path::new("/tmp/work/out.txt"), &allow_list));
+        assert!(is_path_allowed(Path::new("/tmp/work"), &allow_list));
+        assert!(!is_path_allowed(Path::new("/tmp/workshop/out.txt"), &allow_list));
+        assert!(!is_path_allowed(Path::new("/tmp"), &allow_list));
+    }
+
+    #[test]
+    fn resolve_path_handles_dots_without_links()
+    {
+        let root_path = make_root("dots", unsafe { libc::geteuid() });
+
+        let resolved = resolve_path_beneath(&root_path, Path::new("/work"), Path::new("./new/../out.txt"));
+        assert_eq!(resolved, Some(PathBuf::from("/work/out.txt")));
+
+        let resolved = resolve_path_beneath(&root_path, Path::new("/work"), Path::new("../../../etc/shadow"));
+        assert_eq!(resolved, Some(PathBuf::from("/etc/shadow")));
+
+        std::fs::remove_dir_all(root_path).unwrap();
+    }
+
+    #[test]
+    fn resolve_path_denies_planted_link()
+    {
+        // Link created by the program is owned by its user, never by root
+        let root_path = make_root("planted", if is_root() { 65534 } else { unsafe { libc::geteuid() } });
+
+        assert_eq!(resolve_path_beneath(&root_path, Path::new("/work"), Path::new("link/shadow")), None);
+        assert_eq!(resolve_path_beneath(&root_path, Path::new("/"), Path::new("/work/link/../etc/shadow")), None);
+        assert_eq!(resolve_path_beneath(&root_path, Path::new("/work"), Path::new("link")), None);
+
+        std::fs::remove_dir_all(root_path).unwrap();
+    }
+
+    #[test]
+    fn resolve_path_follows_root_link()
+    {
+        // Links owned by root can't be created without privileges
+        if !is_root() { return; }
+        let root_path = make_root("system", 0);
+
+        // Resolved path points at the target, so it is checked against the policy instead of the link
+        let resolved = resolve_path_beneath(&root_path, Path::new("/work"), Path::new("link/shadow"));
+        assert_eq!(resolved, Some(PathBuf::from("/etc/shadow")));
+        assert!(!is_path_allowed(&resolved.unwrap(), &[PathBuf::from("/work")]));
+
+        // Parent directory of a link is the parent of its target, not the directory of the link
+        let resolved = resolve_path_beneath(&root_path, Path::new("/work"), Path::new("link/../work/out.txt"));
+        assert_eq!(resolved, Some(PathBuf::from("/work/out.txt")));
+
+        let resolved = resolve_path_beneath(&root_path, Path::new("/work"), Path::new("link/../secret"));
+        assert_eq!(resolved, Some(PathBuf::from("/secret")));
+
+        std::fs::remove_dir_all(root_path).unwrap();
+    }
+}
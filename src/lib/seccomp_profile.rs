@@ -0,0 +1,264 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CStr;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use libc::{c_char, c_int, c_long};
+use crate::seccomp_context::{arg_equal, get_syscall_name, get_syscall_number, ScmpContext, SCMP_ACT_ALLOW};
+
+/*
+ * System calls used by LIMTRAC itself between the moment it starts learning
+ * and execution of the program. They are not reported by the supervisor, so
+ * we add them to every learned profile, otherwise it can't be enforced. The
+ * program may also reuse the number of the (close-on-exec) channel socket for
+ * its own one, so its `sendmsg` calls on it are not reported too.
+ */
+const PROFILE_BASE_SYSCALLS : [c_long; 2] = [libc::SYS_close, libc::SYS_sendmsg];
+
+/* @System calls having an argument that selects an operation to perform */
+fn get_selector_arg(sys_call: c_long) -> Option<usize>
+{
+    match sys_call {
+        libc::SYS_socket | libc::SYS_prctl | libc::SYS_personality => Some(0),
+        libc::SYS_ioctl | libc::SYS_fcntl => Some(1),
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_arch_prctl => Some(0),
+        _ => None
+    }
+}
+/* @/System calls having an argument that selects an operation to perform */
+
+/*
+ * A list of system calls used by a program during a reference run, together with their
+ * notable arguments. Values of selector arguments are enforced when the profile is used
+ * as an allowlist, while paths are informational (see `scmp_path_policy` to check them).
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyscallProfile
+{
+    pub syscalls : BTreeMap<String, SyscallProfileEntry>
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyscallProfileEntry
+{
+    pub arg_index : Option<usize>,
+    pub arg_values : BTreeSet<u64>,
+    pub paths : BTreeSet<PathBuf>
+}
+
+impl SyscallProfile
+{
+    pub(crate) fn new_learning() -> Self
+    {
+        let mut profile = Self::default();
+
+        for sys_call in PROFILE_BASE_SYSCALLS
+        { profile.record(sys_call as c_int, &[0; 6], None); }
+
+        profile
+    }
+
+    pub(crate) fn record(&mut self, sys_call: c_int, args: &[u64; 6], path: Option<PathBuf>)
+    {
+        let sys_name = match get_syscall_name(sys_call as c_long) {
+            Some(name) => name,
+            None => return
+        };
+
+        let entry = self.syscalls.entry(sys_name).or_default();
+
+        // Base system calls are recorded without arguments, so they are allowed unconditionally
+        if PROFILE_BASE_SYSCALLS.contains(&(sys_call as c_long)) { return; }
+
+        if let Some(arg_index) = get_selector_arg(sys_call as c_long) {
+            entry.arg_index = Some(arg_index);
+            entry.arg_values.insert(args[arg_index]);
+        }
+
+        if let Some(path) = path { entry.paths.insert(path); }
+    }
+
+    /*
+     * Text representation of the profile: a name of a system call per line, followed
+     * by indented `key = value` lines describing notable arguments of the call.
+     */
+    pub fn to_text(&self) -> String
+    {
+        let mut result = String::from("# LIMTRAC system calls allowlist profile\n");
+
+        for (sys_name, entry) in &self.syscalls {
+            let _ = writeln!(result, "{}", sys_name);
+
+            if let Some(arg_index) = entry.arg_index {
+                for arg_value in &entry.arg_values
+                { let _ = writeln!(result, "    arg{} = {:#x}", arg_index, arg_value); }
+            }
+
+            for path in &entry.paths
+            { let _ = writeln!(result, "    path = {}", path.display()); }
+        }
+
+        result
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String>
+    {
+        let mut profile = Self::default();
+        let mut current : Option<&mut SyscallProfileEntry> = None;
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_trimmed = line.trim();
+            if line_trimmed.is_empty() || line_trimmed.starts_with('#') { continue; }
+
+            // Not indented lines contain names of system calls
+            if !line.starts_with(char::is_whitespace) {
+                current = Some(profile.syscalls.entry(line_trimmed.to_owned()).or_default());
+                continue;
+            }
+
+            let invalid_line = || format!("Invalid profile entry on line {}", line_index + 1);
+            let entry = current.as_mut().ok_or_else(invalid_line)?;
+            let (key, value) = line_trimmed.split_once(" = ").ok_or_else(invalid_line)?;
+
+            if key == "path" { entry.paths.insert(PathBuf::from(value)); }
+            else if let Some(arg_index) = key.strip_prefix("arg") {
+                let arg_index = arg_index.parse::<usize>().ok().filter(|index| *index < 6).ok_or_else(invalid_line)?;
+                let arg_value = u64::from_str_radix(value.trim_start_matches("0x"), 16).map_err(|_| invalid_line())?;
+
+                if entry.arg_index.is_some_and(|index| index != arg_index) { return Err(invalid_line()); }
+
+                entry.arg_index = Some(arg_index);
+                entry.arg_values.insert(arg_value);
+            }
+            else { return Err(invalid_line()); }
+        }
+
+        Ok(profile)
+    }
+
+    pub fn save(&self, file_path: &Path) -> std::io::Result<()>
+    {
+        std::fs::write(file_path, self.to_text())
+    }
+
+    pub fn load(file_path: &Path) -> std::io::Result<Self>
+    {
+        let text = std::fs::read_to_string(file_path)?;
+        Self::from_text(&text).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /* @Add rules allowing system calls from the profile to a SECCOMP context */
+    pub(crate) fn apply(&self, ctx: &mut ScmpContext)
+    {
+        for (sys_name, entry) in &self.syscalls {
+            let sys_call = match get_syscall_number(sys_name) {
+                Some(sys_call) => sys_call,
+                None => panic!("Cannot allow unknown system call '{}' listed in the profile!", sys_name)
+            };
+
+            let result = match entry.arg_index {
+                Some(arg_index) if !entry.arg_values.is_empty() => entry.arg_values.iter().try_for_each(|arg_value| {
+                    ctx.add_rule(SCMP_ACT_ALLOW, sys_call, &[arg_equal(arg_index as u32, *arg_value)])
+                }),
+                _ => ctx.add_rule(SCMP_ACT_ALLOW, sys_call, &[])
+            };
+
+            if let Err(err) = result
+            { panic!("Cannot add new SECCOMP rule for system call '{}': {}", sys_name, err) }
+        }
+    }
+    /* @/Add rules allowing system calls from the profile to a SECCOMP context */
+}
+
+/* @Load a profile, specified by the execution request */
+pub(crate) fn load_requested_profile(profile_path: *const c_char) -> Option<SyscallProfile>
+{
+    let profile_path = unsafe { CStr::from_ptr(profile_path) };
+    if profile_path.to_bytes().is_empty() { return None; }
+
+    let profile_path = Path::new(profile_path.to_str().unwrap());
+
+    match SyscallProfile::load(profile_path) {
+        Ok(profile) => Some(profile),
+        Err(err) => panic!("Cannot load system calls profile '{}': {}", profile_path.display(), err)
+    }
+}
+/* @/Load a profile, specified by the execution request */
+
+pub(crate) fn save_requested_profile(profile: &SyscallProfile, profile_path: *const c_char)
+{
+    let profile_path = unsafe { CStr::from_ptr(profile_path) };
+    if profile_path.to_bytes().is_empty() { return; }
+
+    let profile_path = Path::new(profile_path.to_str().unwrap());
+
+    if let Err(err) = profile.save(profile_path)
+    { panic!("Cannot save system calls profile '{}': {}", profile_path.display(), err) }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::PathBuf;
+    use super::SyscallProfile;
+
+    #[test]
+    fn profile_text_round_trip()
+    {
+        let mut profile = SyscallProfile::new_learning();
+        profile.record(libc::SYS_openat as i32, &[0; 6], Some(PathBuf::from("/etc/hostname")));
+        profile.record(libc::SYS_ioctl as i32, &[1, libc::TCGETS, 0, 0, 0, 0], None);
+        profile.record(libc::SYS_ioctl as i32, &[1, libc::FIONREAD, 0, 0, 0, 0], None);
+        profile.record(libc::SYS_close as i32, &[3, 0, 0, 0, 0, 0], None);
+
+        let text = profile.to_text();
+        assert!(text.contains("ioctl\n    arg1 = 0x5401\n"));
+        assert!(text.contains("openat\n    path = /etc/hostname\n"));
+
+        // Base system calls are allowed without arguments, even if they were not reported
+        let close_entry = &profile.syscalls["close"];
+        assert!(close_entry.arg_index.is_none() && close_entry.arg_values.is_empty());
+        assert!(profile.syscalls.contains_key("sendmsg"));
+
+        assert_eq!(SyscallProfile::from_text(&text), Ok(profile));
+    }
+
+    #[test]
+    fn profile_text_skips_comments()
+    {
+        let profile = SyscallProfile::from_text("# comment\n\nread\n    # comment\nfcntl\n  arg1 = 3\n").unwrap();
+
+        assert_eq!(profile.syscalls.len(), 2);
+        assert_eq!(profile.syscalls["fcntl"].arg_index, Some(1));
+        assert!(profile.syscalls["fcntl"].arg_values.contains(&3));
+    }
+
+    #[test]
+    fn profile_text_rejects_invalid_entries()
+    {
+        assert!(SyscallProfile::from_text("    path = /etc\n").is_err());
+        assert!(SyscallProfile::from_text("ioctl\n    arg6 = 0x1\n").is_err());
+        assert!(SyscallProfile::from_text("ioctl\n    arg1 = 0x1\n    arg2 = 0x2\n").is_err());
+        assert!(SyscallProfile::from_text("ioctl\n    arg1 = 0xzz\n").is_err());
+        assert!(SyscallProfile::from_text("ioctl\n    mode = 0x1\n").is_err());
+        assert_eq!(SyscallProfile::from_text("read\n  path /etc\n"), Err(String::from("Invalid profile entry on line 2")));
+    }
+}
@@ -0,0 +1,189 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::{c_int, c_long};
+use crate::constants::{SCMP_DENY_TIER_BASELINE, SCMP_DENY_TIER_HARDENED, SCMP_DENY_TIER_PARANOID};
+use crate::seccomp_context::{ScmpContext, SCMP_ACT_ERRNO, SCMP_ACT_KILL_PROCESS};
+
+/*
+ * A single entry of the system calls denylist. By default, a denied call kills the
+ * process, but it can fail with the specified error instead (so C libraries can fall
+ * back to other calls). Some rules match only calls with certain flags in first argument.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DenyRule
+{
+    pub sys_call    : c_long,            // Native number of the system call
+    pub tier        : c_int,             // The lowest tier, the rule belongs to
+    pub errno       : Option<c_int>,     // Error returned instead of killing the process
+    pub arg0_masked : Option<(u64, u64)> // Mask and value of the first argument to match
+}
+
+const fn kill(sys_call: c_long, tier: c_int) -> DenyRule
+{ DenyRule { sys_call, tier, errno: None, arg0_masked: None } }
+
+const fn kill_masked(sys_call: c_long, tier: c_int, mask: u64, value: u64) -> DenyRule
+{ DenyRule { sys_call, tier, errno: None, arg0_masked: Some((mask, value)) } }
+
+const fn fail(sys_call: c_long, tier: c_int, errno: c_int) -> DenyRule
+{ DenyRule { sys_call, tier, errno: Some(errno), arg0_masked: None } }
+
+const BASELINE : c_int = SCMP_DENY_TIER_BASELINE;
+const HARDENED : c_int = SCMP_DENY_TIER_HARDENED;
+const PARANOID : c_int = SCMP_DENY_TIER_PARANOID;
+
+/* @Denylist of system calls, available on every architecture */
+const DENY_RULES : &[DenyRule] = &[
+    // Deny creating child processes, changing process ownership, etc.
+    kill(libc::SYS_reboot, BASELINE), kill(libc::SYS_setuid, BASELINE), kill(libc::SYS_setgid, BASELINE),
+    kill(libc::SYS_prctl, BASELINE), kill(libc::SYS_unshare, BASELINE), kill(libc::SYS_setrlimit, BASELINE),
+
+    // Operations on per-process timer are denied
+    kill(libc::SYS_timer_create, BASELINE), kill(libc::SYS_timer_gettime, BASELINE),
+    kill(libc::SYS_timer_settime, BASELINE), kill(libc::SYS_timer_delete, BASELINE),
+    kill(libc::SYS_timer_getoverrun, BASELINE), kill(libc::SYS_timerfd_create, BASELINE),
+    kill(libc::SYS_timerfd_gettime, BASELINE), kill(libc::SYS_timerfd_settime, BASELINE),
+
+    // Deny making unwanted changes to filesystem
+    kill(libc::SYS_chdir, BASELINE), kill(libc::SYS_fchdir, BASELINE), kill(libc::SYS_fchmod, BASELINE),
+    kill(libc::SYS_fchmodat, BASELINE), kill(libc::SYS_fchown, BASELINE), kill(libc::SYS_fchownat, BASELINE),
+
+    // Deny inspecting and modifying other processes
+    kill(libc::SYS_ptrace, HARDENED), kill(libc::SYS_process_vm_readv, HARDENED),
+    kill(libc::SYS_process_vm_writev, HARDENED), kill(libc::SYS_pidfd_getfd, HARDENED),
+    kill(libc::SYS_kcmp, HARDENED),
+
+    // Deny changing filesystem layout visible to the process
+    kill(libc::SYS_mount, HARDENED), kill(libc::SYS_umount2, HARDENED), kill(libc::SYS_pivot_root, HARDENED),
+    kill(libc::SYS_chroot, HARDENED), kill(libc::SYS_move_mount, HARDENED), kill(libc::SYS_open_tree, HARDENED),
+    kill(libc::SYS_fsopen, HARDENED), kill(libc::SYS_fsconfig, HARDENED), kill(libc::SYS_fsmount, HARDENED),
+    kill(libc::SYS_fspick, HARDENED), kill(libc::SYS_mount_setattr, HARDENED),
+
+    // Deny kernel interfaces, often used to escalate privileges
+    kill(libc::SYS_bpf, HARDENED), kill(libc::SYS_perf_event_open, HARDENED), kill(libc::SYS_keyctl, HARDENED),
+    kill(libc::SYS_add_key, HARDENED), kill(libc::SYS_request_key, HARDENED), kill(libc::SYS_userfaultfd, HARDENED),
+    kill(libc::SYS_io_uring_setup, HARDENED), kill(libc::SYS_io_uring_enter, HARDENED),
+    kill(libc::SYS_io_uring_register, HARDENED),
+
+    // Deny loading kernels and kernel modules
+    kill(libc::SYS_kexec_load, HARDENED), kill(libc::SYS_kexec_file_load, HARDENED),
+    kill(libc::SYS_init_module, HARDENED), kill(libc::SYS_finit_module, HARDENED),
+    kill(libc::SYS_delete_module, HARDENED),
+
+    // Deny entering namespaces and changing execution domain of the process
+    kill(libc::SYS_setns, HARDENED), kill(libc::SYS_personality, HARDENED),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWNS as u64, libc::CLONE_NEWNS as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWCGROUP as u64, libc::CLONE_NEWCGROUP as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWUTS as u64, libc::CLONE_NEWUTS as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWIPC as u64, libc::CLONE_NEWIPC as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWUSER as u64, libc::CLONE_NEWUSER as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWPID as u64, libc::CLONE_NEWPID as u64),
+    kill_masked(libc::SYS_clone, HARDENED, libc::CLONE_NEWNET as u64, libc::CLONE_NEWNET as u64),
+
+    // Flags of `clone3` are passed in memory, which can't be checked, so C libraries fall back to `clone`
+    fail(libc::SYS_clone3, HARDENED, libc::ENOSYS),
+
+    // Deny changing credentials and capabilities of the process
+    kill(libc::SYS_setreuid, HARDENED), kill(libc::SYS_setregid, HARDENED), kill(libc::SYS_setresuid, HARDENED),
+    kill(libc::SYS_setresgid, HARDENED), kill(libc::SYS_setgroups, HARDENED), kill(libc::SYS_setfsuid, HARDENED),
+    kill(libc::SYS_setfsgid, HARDENED), kill(libc::SYS_capset, HARDENED),
+
+    // Deny administering the system
+    kill(libc::SYS_swapon, HARDENED), kill(libc::SYS_swapoff, HARDENED), kill(libc::SYS_syslog, HARDENED),
+    kill(libc::SYS_acct, HARDENED), kill(libc::SYS_quotactl, HARDENED), kill(libc::SYS_settimeofday, HARDENED),
+    kill(libc::SYS_clock_settime, HARDENED), kill(libc::SYS_adjtimex, HARDENED),
+    kill(libc::SYS_clock_adjtime, HARDENED), kill(libc::SYS_sethostname, HARDENED),
+    kill(libc::SYS_setdomainname, HARDENED), kill(libc::SYS_vhangup, HARDENED),
+
+    // Deny bypassing path checks and watching the whole filesystem
+    kill(libc::SYS_open_by_handle_at, HARDENED), kill(libc::SYS_name_to_handle_at, HARDENED),
+    kill(libc::SYS_fanotify_init, HARDENED), kill(libc::SYS_lookup_dcookie, HARDENED),
+    kill(libc::SYS_mknodat, HARDENED),
+
+    // Deny network and local sockets
+    kill(libc::SYS_socket, PARANOID), kill(libc::SYS_socketpair, PARANOID),
+
+    // Deny creating child processes, but allow creating threads
+    kill_masked(libc::SYS_clone, PARANOID, libc::CLONE_THREAD as u64, 0),
+
+    // Deny changing scheduling parameters and locking memory
+    kill(libc::SYS_sched_setaffinity, PARANOID), kill(libc::SYS_sched_setscheduler, PARANOID),
+    kill(libc::SYS_sched_setparam, PARANOID), kill(libc::SYS_sched_setattr, PARANOID),
+    kill(libc::SYS_setpriority, PARANOID), kill(libc::SYS_ioprio_set, PARANOID), kill(libc::SYS_mlock, PARANOID),
+    kill(libc::SYS_mlock2, PARANOID), kill(libc::SYS_mlockall, PARANOID),
+
+    // Deny System V and POSIX inter-process communication
+    kill(libc::SYS_msgget, PARANOID), kill(libc::SYS_semget, PARANOID), kill(libc::SYS_shmget, PARANOID),
+    kill(libc::SYS_mq_open, PARANOID),
+
+    // Deny any modification of directories and watching them
+    kill(libc::SYS_linkat, PARANOID), kill(libc::SYS_symlinkat, PARANOID), kill(libc::SYS_renameat, PARANOID),
+    kill(libc::SYS_renameat2, PARANOID), kill(libc::SYS_unlinkat, PARANOID), kill(libc::SYS_mkdirat, PARANOID),
+    kill(libc::SYS_inotify_init1, PARANOID)
+];
+/* @/Denylist of system calls, available on every architecture */
+
+/* @Denylist of legacy system calls, available on some architectures */
+#[cfg(target_arch = "x86_64")]
+const DENY_RULES_ARCH : &[DenyRule] = &[
+    kill(libc::SYS_chmod, BASELINE), kill(libc::SYS_chown, BASELINE), kill(libc::SYS_lchown, BASELINE),
+
+    kill(libc::SYS_mknod, HARDENED), kill(libc::SYS_iopl, HARDENED), kill(libc::SYS_ioperm, HARDENED),
+    kill(libc::SYS_uselib, HARDENED), kill(libc::SYS__sysctl, HARDENED),
+
+    kill(libc::SYS_fork, PARANOID), kill(libc::SYS_vfork, PARANOID), kill(libc::SYS_link, PARANOID),
+    kill(libc::SYS_symlink, PARANOID), kill(libc::SYS_rename, PARANOID), kill(libc::SYS_unlink, PARANOID),
+    kill(libc::SYS_mkdir, PARANOID), kill(libc::SYS_rmdir, PARANOID), kill(libc::SYS_inotify_init, PARANOID)
+];
+
+#[cfg(not(target_arch = "x86_64"))]
+const DENY_RULES_ARCH : &[DenyRule] = &[];
+/* @/Denylist of legacy system calls, available on some architectures */
+
+/* @Rules of the specified tier, including rules of all lower tiers */
+pub fn get_deny_rules(deny_tier: c_int) -> Vec<DenyRule>
+{
+    DENY_RULES.iter().chain(DENY_RULES_ARCH)
+        .filter(|rule| rule.tier <= deny_tier)
+        .copied()
+        .collect()
+}
+/* @/Rules of the specified tier, including rules of all lower tiers */
+
+pub(crate) fn is_valid_deny_tier(deny_tier: c_int) -> bool
+{
+    (SCMP_DENY_TIER_BASELINE..=SCMP_DENY_TIER_PARANOID).contains(&deny_tier)
+}
+
+pub(crate) fn apply_deny_rules(ctx: &mut ScmpContext, deny_tier: c_int)
+{
+    for rule in get_deny_rules(deny_tier) {
+        let action = match rule.errno {
+            Some(errno) => SCMP_ACT_ERRNO(errno as u32),
+            None => SCMP_ACT_KILL_PROCESS
+        };
+
+        let comparators = match rule.arg0_masked {
+            Some((mask, value)) => vec![crate::seccomp_context::arg_masked_equal(0, mask, value)],
+            None => vec![]
+        };
+
+        if let Err(err) = ctx.add_rule(action, rule.sys_call, &comparators)
+        { panic!("Cannot add new SECCOMP filter for system call '{}': {}", rule.sys_call, err) }
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::{c_int, pid_t};
+use crate::SYS_EXEC_FAILED;
+
+/*
+ * Child process can't return errors to the caller, so it reports the failed setup
+ * stage and its `errno` over a pipe before it exits, as well as created namespaces
+ * and the PID of the program forked in a PID namespace. Both ends are closed on exec,
+ * and the parent process collects messages without blocking while it waits.
+ */
+
+const SETUP_MESSAGE_FAILURE : c_int = 0;
+const SETUP_MESSAGE_NAMESPACES : c_int = 1;
+const SETUP_MESSAGE_PROGRAM : c_int = 2;
+
+pub struct SetupReport
+{
+    pub failure         : Option<(c_int, c_int)>, // Failed stage and its `errno`, if any
+    pub unshare_created : c_int,                  // Namespaces created, see `UNSHARE_NS_*`
+    pub program_pid     : Option<pid_t>           // Program PID in the namespace of the parent process
+}
+
+pub struct SetupChannel
+{
+    read_fd      : c_int,
+    write_fd     : c_int,
+    setup_report : SetupReport // Messages received so far
+}
+
+impl SetupChannel
+{
+    pub fn new(fd_min : c_int) -> Self
+    {
+        let mut pipe_fds : [c_int; 2] = [-1, -1];
+
+        if unsafe { libc::pipe2(pipe_fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } == SYS_EXEC_FAILED
+        { crate::helper_functions::panic_on_syscall!("pipe2"); }
+
+        Self {
+            read_fd: crate::helper_functions::move_fd_above(pipe_fds[0], fd_min),
+            write_fd: crate::helper_functions::move_fd_above(pipe_fds[1], fd_min),
+            setup_report: SetupReport { failure: None, unshare_created: 0, program_pid: None }
+        }
+    }
+
+    /* [CHILD] Reports a failed stage (see `SETUP_FAILURE_*`) with the current `errno` and exits */
+    pub fn report_failure(&self, setup_failure : c_int) -> !
+    {
+        self.send_message([SETUP_MESSAGE_FAILURE, setup_failure, nix::errno::errno()]);
+        unsafe { libc::_exit(SYS_EXEC_FAILED) }
+    }
+
+    /* [CHILD] Reports namespaces created for the program (see `UNSHARE_NS_*`) */
+    pub fn report_namespaces(&self, unshare_created : c_int)
+    {
+        self.send_message([SETUP_MESSAGE_NAMESPACES, unshare_created, 0]);
+    }
+
+    /* [PROGRAM] Reports the PID of a program forked by the init process of a PID namespace */
+    pub fn report_program_pid(&self, program_pid : pid_t)
+    {
+        self.send_message([SETUP_MESSAGE_PROGRAM, program_pid, 0]);
+    }
+
+    fn send_message(&self, message : [c_int; 3])
+    {
+        unsafe { libc::write(self.write_fd, message.as_ptr() as *const libc::c_void, size_of_val(&message)) };
+    }
+
+    /* [PARENT] Closes the end used by the child process, right after it was forked */
+    pub fn close_sender(&mut self)
+    {
+        unsafe { libc::close(self.write_fd) };
+        self.write_fd = SYS_EXEC_FAILED;
+    }
+
+    /* [PARENT] Returns everything the child process reported so far */
+    pub fn receive_report(&mut self) -> &SetupReport
+    {
+        let mut message : [c_int; 3] = [0; 3];
+
+        while unsafe { libc::read(self.read_fd, message.as_mut_ptr() as *mut libc::c_void, size_of_val(&message)) }
+            == size_of_val(&message) as isize
+        {
+            match message[0] {
+                SETUP_MESSAGE_FAILURE => self.setup_report.failure = Some((message[1], message[2])),
+                SETUP_MESSAGE_NAMESPACES => self.setup_report.unshare_created = message[1],
+                SETUP_MESSAGE_PROGRAM => self.setup_report.program_pid = Some(message[1]),
+                _ => {}
+            }
+        }
+
+        &self.setup_report
+    }
+}
+
+impl Drop for SetupChannel
+{
+    fn drop(&mut self)
+    {
+        unsafe {
+            libc::close(self.read_fd);
+            if self.write_fd != SYS_EXEC_FAILED { libc::close(self.write_fd); }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::c_long;
+use crate::seccomp_bpf::{AUDIT_ARCH_COMPAT, AUDIT_ARCH_NATIVE};
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+mod x86;
+#[cfg(target_arch = "x86_64")]
+mod x32;
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "aarch64")]
+mod arm;
+
+/*
+ * System calls table of a single ABI. The x32 ABI shares the architecture identifier
+ * with x86-64, so its calls are distinguished by `X32_SYSCALL_BIT` in numbers instead.
+ */
+pub(crate) struct SyscallTable
+{
+    pub audit_arch : u32,                           // Value of `arch` field of `struct seccomp_data`
+    pub x32_abi    : bool,                          // Table contains x32 system calls
+    pub syscalls   : &'static [(&'static str, c_long)]
+}
+
+impl SyscallTable
+{
+    pub fn get_number(&self, sys_name: &str) -> Option<c_long>
+    {
+        self.syscalls.iter().find(|(name, _)| *name == sys_name).map(|(_, sys_call)| *sys_call)
+    }
+
+    pub fn get_name(&self, sys_call: c_long) -> Option<&'static str>
+    {
+        self.syscalls.iter().find(|(_, number)| *number == sys_call).map(|(name, _)| *name)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(crate) const NATIVE_TABLE : SyscallTable = SyscallTable { audit_arch: AUDIT_ARCH_NATIVE, x32_abi: false, syscalls: x86_64::SYSCALLS };
+#[cfg(target_arch = "x86_64")]
+pub(crate) const COMPAT_TABLES : [SyscallTable; 2] = [
+    SyscallTable { audit_arch: AUDIT_ARCH_COMPAT[0], x32_abi: false, syscalls: x86::SYSCALLS },
+    SyscallTable { audit_arch: AUDIT_ARCH_NATIVE, x32_abi: true, syscalls: x32::SYSCALLS }
+];
+
+#[cfg(target_arch = "aarch64")]
+pub(crate) const NATIVE_TABLE : SyscallTable = SyscallTable { audit_arch: AUDIT_ARCH_NATIVE, x32_abi: false, syscalls: aarch64::SYSCALLS };
+#[cfg(target_arch = "aarch64")]
+pub(crate) const COMPAT_TABLES : [SyscallTable; 1] = [
+    SyscallTable { audit_arch: AUDIT_ARCH_COMPAT[0], x32_abi: false, syscalls: arm::SYSCALLS }
+];
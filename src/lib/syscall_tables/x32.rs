@@ -0,0 +1,128 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use libc::c_long;
+
+/*
+ * Names and numbers of system calls of the x32 ABI, sorted by number.
+ * Names match ones used by libseccomp, so profiles are portable between backends.
+ */
+pub(crate) const SYSCALLS : &[(&str, c_long)] = &[
+    ("read", 0x40000000), ("write", 0x40000001), ("open", 0x40000002), ("close", 0x40000003),
+    ("stat", 0x40000004), ("fstat", 0x40000005), ("lstat", 0x40000006), ("poll", 0x40000007),
+    ("lseek", 0x40000008), ("mmap", 0x40000009), ("mprotect", 0x4000000a), ("munmap", 0x4000000b),
+    ("brk", 0x4000000c), ("rt_sigprocmask", 0x4000000e), ("pread64", 0x40000011), ("pwrite64", 0x40000012),
+    ("access", 0x40000015), ("pipe", 0x40000016), ("select", 0x40000017), ("sched_yield", 0x40000018),
+    ("mremap", 0x40000019), ("msync", 0x4000001a), ("mincore", 0x4000001b), ("madvise", 0x4000001c),
+    ("shmget", 0x4000001d), ("shmat", 0x4000001e), ("shmctl", 0x4000001f), ("dup", 0x40000020),
+    ("dup2", 0x40000021), ("pause", 0x40000022), ("nanosleep", 0x40000023), ("getitimer", 0x40000024),
+    ("alarm", 0x40000025), ("setitimer", 0x40000026), ("getpid", 0x40000027), ("sendfile", 0x40000028),
+    ("socket", 0x40000029), ("connect", 0x4000002a), ("accept", 0x4000002b), ("sendto", 0x4000002c),
+    ("shutdown", 0x40000030), ("bind", 0x40000031), ("listen", 0x40000032), ("getsockname", 0x40000033),
+    ("getpeername", 0x40000034), ("socketpair", 0x40000035), ("clone", 0x40000038), ("fork", 0x40000039),
+    ("vfork", 0x4000003a), ("exit", 0x4000003c), ("wait4", 0x4000003d), ("kill", 0x4000003e),
+    ("uname", 0x4000003f), ("semget", 0x40000040), ("semop", 0x40000041), ("semctl", 0x40000042),
+    ("shmdt", 0x40000043), ("msgget", 0x40000044), ("msgsnd", 0x40000045), ("msgrcv", 0x40000046),
+    ("msgctl", 0x40000047), ("fcntl", 0x40000048), ("flock", 0x40000049), ("fsync", 0x4000004a),
+    ("fdatasync", 0x4000004b), ("truncate", 0x4000004c), ("ftruncate", 0x4000004d), ("getdents", 0x4000004e),
+    ("getcwd", 0x4000004f), ("chdir", 0x40000050), ("fchdir", 0x40000051), ("rename", 0x40000052),
+    ("mkdir", 0x40000053), ("rmdir", 0x40000054), ("creat", 0x40000055), ("link", 0x40000056),
+    ("unlink", 0x40000057), ("symlink", 0x40000058), ("readlink", 0x40000059), ("chmod", 0x4000005a),
+    ("fchmod", 0x4000005b), ("chown", 0x4000005c), ("fchown", 0x4000005d), ("lchown", 0x4000005e),
+    ("umask", 0x4000005f), ("gettimeofday", 0x40000060), ("getrlimit", 0x40000061), ("getrusage", 0x40000062),
+    ("sysinfo", 0x40000063), ("times", 0x40000064), ("getuid", 0x40000066), ("syslog", 0x40000067),
+    ("getgid", 0x40000068), ("setuid", 0x40000069), ("setgid", 0x4000006a), ("geteuid", 0x4000006b),
+    ("getegid", 0x4000006c), ("setpgid", 0x4000006d), ("getppid", 0x4000006e), ("getpgrp", 0x4000006f),
+    ("setsid", 0x40000070), ("setreuid", 0x40000071), ("setregid", 0x40000072), ("getgroups", 0x40000073),
+    ("setgroups", 0x40000074), ("setresuid", 0x40000075), ("getresuid", 0x40000076),
+    ("setresgid", 0x40000077), ("getresgid", 0x40000078), ("getpgid", 0x40000079), ("setfsuid", 0x4000007a),
+    ("setfsgid", 0x4000007b), ("getsid", 0x4000007c), ("capget", 0x4000007d), ("capset", 0x4000007e),
+    ("rt_sigsuspend", 0x40000082), ("utime", 0x40000084), ("mknod", 0x40000085), ("personality", 0x40000087),
+    ("ustat", 0x40000088), ("statfs", 0x40000089), ("fstatfs", 0x4000008a), ("sysfs", 0x4000008b),
+    ("getpriority", 0x4000008c), ("setpriority", 0x4000008d), ("sched_setparam", 0x4000008e),
+    ("sched_getparam", 0x4000008f), ("sched_setscheduler", 0x40000090), ("sched_getscheduler", 0x40000091),
+    ("sched_get_priority_max", 0x40000092), ("sched_get_priority_min", 0x40000093),
+    ("sched_rr_get_interval", 0x40000094), ("mlock", 0x40000095), ("munlock", 0x40000096),
+    ("mlockall", 0x40000097), ("munlockall", 0x40000098), ("vhangup", 0x40000099), ("modify_ldt", 0x4000009a),
+    ("pivot_root", 0x4000009b), ("prctl", 0x4000009d), ("arch_prctl", 0x4000009e), ("adjtimex", 0x4000009f),
+    ("setrlimit", 0x400000a0), ("chroot", 0x400000a1), ("sync", 0x400000a2), ("acct", 0x400000a3),
+    ("settimeofday", 0x400000a4), ("mount", 0x400000a5), ("umount2", 0x400000a6), ("swapon", 0x400000a7),
+    ("swapoff", 0x400000a8), ("reboot", 0x400000a9), ("sethostname", 0x400000aa),
+    ("setdomainname", 0x400000ab), ("iopl", 0x400000ac), ("ioperm", 0x400000ad), ("init_module", 0x400000af),
+    ("delete_module", 0x400000b0), ("quotactl", 0x400000b3), ("getpmsg", 0x400000b5), ("putpmsg", 0x400000b6),
+    ("afs_syscall", 0x400000b7), ("tuxcall", 0x400000b8), ("security", 0x400000b9), ("gettid", 0x400000ba),
+    ("readahead", 0x400000bb), ("setxattr", 0x400000bc), ("lsetxattr", 0x400000bd), ("fsetxattr", 0x400000be),
+    ("getxattr", 0x400000bf), ("lgetxattr", 0x400000c0), ("fgetxattr", 0x400000c1), ("listxattr", 0x400000c2),
+    ("llistxattr", 0x400000c3), ("flistxattr", 0x400000c4), ("removexattr", 0x400000c5),
+    ("lremovexattr", 0x400000c6), ("fremovexattr", 0x400000c7), ("tkill", 0x400000c8), ("time", 0x400000c9),
+    ("futex", 0x400000ca), ("sched_setaffinity", 0x400000cb), ("sched_getaffinity", 0x400000cc),
+    ("io_destroy", 0x400000cf), ("io_getevents", 0x400000d0), ("io_cancel", 0x400000d2),
+    ("lookup_dcookie", 0x400000d4), ("epoll_create", 0x400000d5), ("remap_file_pages", 0x400000d8),
+    ("getdents64", 0x400000d9), ("set_tid_address", 0x400000da), ("restart_syscall", 0x400000db),
+    ("semtimedop", 0x400000dc), ("fadvise64", 0x400000dd), ("timer_settime", 0x400000df),
+    ("timer_gettime", 0x400000e0), ("timer_getoverrun", 0x400000e1), ("timer_delete", 0x400000e2),
+    ("clock_settime", 0x400000e3), ("clock_gettime", 0x400000e4), ("clock_getres", 0x400000e5),
+    ("clock_nanosleep", 0x400000e6), ("exit_group", 0x400000e7), ("epoll_wait", 0x400000e8),
+    ("epoll_ctl", 0x400000e9), ("tgkill", 0x400000ea), ("utimes", 0x400000eb), ("mbind", 0x400000ed),
+    ("set_mempolicy", 0x400000ee), ("get_mempolicy", 0x400000ef), ("mq_open", 0x400000f0),
+    ("mq_unlink", 0x400000f1), ("mq_timedsend", 0x400000f2), ("mq_timedreceive", 0x400000f3),
+    ("mq_getsetattr", 0x400000f5), ("add_key", 0x400000f8), ("request_key", 0x400000f9),
+    ("keyctl", 0x400000fa), ("ioprio_set", 0x400000fb), ("ioprio_get", 0x400000fc),
+    ("inotify_init", 0x400000fd), ("inotify_add_watch", 0x400000fe), ("inotify_rm_watch", 0x400000ff),
+    ("migrate_pages", 0x40000100), ("openat", 0x40000101), ("mkdirat", 0x40000102), ("mknodat", 0x40000103),
+    ("fchownat", 0x40000104), ("futimesat", 0x40000105), ("newfstatat", 0x40000106), ("unlinkat", 0x40000107),
+    ("renameat", 0x40000108), ("linkat", 0x40000109), ("symlinkat", 0x4000010a), ("readlinkat", 0x4000010b),
+    ("fchmodat", 0x4000010c), ("faccessat", 0x4000010d), ("pselect6", 0x4000010e), ("ppoll", 0x4000010f),
+    ("unshare", 0x40000110), ("splice", 0x40000113), ("tee", 0x40000114), ("sync_file_range", 0x40000115),
+    ("utimensat", 0x40000118), ("epoll_pwait", 0x40000119), ("signalfd", 0x4000011a),
+    ("timerfd_create", 0x4000011b), ("eventfd", 0x4000011c), ("fallocate", 0x4000011d),
+    ("timerfd_settime", 0x4000011e), ("timerfd_gettime", 0x4000011f), ("accept4", 0x40000120),
+    ("signalfd4", 0x40000121), ("eventfd2", 0x40000122), ("epoll_create1", 0x40000123), ("dup3", 0x40000124),
+    ("pipe2", 0x40000125), ("inotify_init1", 0x40000126), ("perf_event_open", 0x4000012a),
+    ("fanotify_init", 0x4000012c), ("fanotify_mark", 0x4000012d), ("prlimit64", 0x4000012e),
+    ("name_to_handle_at", 0x4000012f), ("open_by_handle_at", 0x40000130), ("clock_adjtime", 0x40000131),
+    ("syncfs", 0x40000132), ("setns", 0x40000134), ("getcpu", 0x40000135), ("kcmp", 0x40000138),
+    ("finit_module", 0x40000139), ("sched_setattr", 0x4000013a), ("sched_getattr", 0x4000013b),
+    ("renameat2", 0x4000013c), ("seccomp", 0x4000013d), ("getrandom", 0x4000013e),
+    ("memfd_create", 0x4000013f), ("kexec_file_load", 0x40000140), ("bpf", 0x40000141),
+    ("userfaultfd", 0x40000143), ("membarrier", 0x40000144), ("mlock2", 0x40000145),
+    ("copy_file_range", 0x40000146), ("pkey_mprotect", 0x40000149), ("pkey_alloc", 0x4000014a),
+    ("pkey_free", 0x4000014b), ("statx", 0x4000014c), ("io_pgetevents", 0x4000014d), ("rseq", 0x4000014e),
+    ("pidfd_send_signal", 0x400001a8), ("io_uring_setup", 0x400001a9), ("io_uring_enter", 0x400001aa),
+    ("io_uring_register", 0x400001ab), ("open_tree", 0x400001ac), ("move_mount", 0x400001ad),
+    ("fsopen", 0x400001ae), ("fsconfig", 0x400001af), ("fsmount", 0x400001b0), ("fspick", 0x400001b1),
+    ("pidfd_open", 0x400001b2), ("clone3", 0x400001b3), ("close_range", 0x400001b4), ("openat2", 0x400001b5),
+    ("pidfd_getfd", 0x400001b6), ("faccessat2", 0x400001b7), ("process_madvise", 0x400001b8),
+    ("epoll_pwait2", 0x400001b9), ("mount_setattr", 0x400001ba), ("quotactl_fd", 0x400001bb),
+    ("landlock_create_ruleset", 0x400001bc), ("landlock_add_rule", 0x400001bd),
+    ("landlock_restrict_self", 0x400001be), ("memfd_secret", 0x400001bf), ("process_mrelease", 0x400001c0),
+    ("futex_waitv", 0x400001c1), ("set_mempolicy_home_node", 0x400001c2), ("cachestat", 0x400001c3),
+    ("fchmodat2", 0x400001c4), ("futex_wake", 0x400001c6), ("futex_wait", 0x400001c7),
+    ("futex_requeue", 0x400001c8), ("rt_sigaction", 0x40000200), ("rt_sigreturn", 0x40000201),
+    ("ioctl", 0x40000202), ("readv", 0x40000203), ("writev", 0x40000204), ("recvfrom", 0x40000205),
+    ("sendmsg", 0x40000206), ("recvmsg", 0x40000207), ("execve", 0x40000208), ("ptrace", 0x40000209),
+    ("rt_sigpending", 0x4000020a), ("rt_sigtimedwait", 0x4000020b), ("rt_sigqueueinfo", 0x4000020c),
+    ("sigaltstack", 0x4000020d), ("timer_create", 0x4000020e), ("mq_notify", 0x4000020f),
+    ("kexec_load", 0x40000210), ("waitid", 0x40000211), ("set_robust_list", 0x40000212),
+    ("get_robust_list", 0x40000213), ("vmsplice", 0x40000214), ("move_pages", 0x40000215),
+    ("preadv", 0x40000216), ("pwritev", 0x40000217), ("rt_tgsigqueueinfo", 0x40000218),
+    ("recvmmsg", 0x40000219), ("sendmmsg", 0x4000021a), ("process_vm_readv", 0x4000021b),
+    ("process_vm_writev", 0x4000021c), ("setsockopt", 0x4000021d), ("getsockopt", 0x4000021e),
+    ("io_setup", 0x4000021f), ("io_submit", 0x40000220), ("execveat", 0x40000221), ("preadv2", 0x40000222),
+    ("pwritev2", 0x40000223)
+];
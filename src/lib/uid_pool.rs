@@ -0,0 +1,212 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::CStr;
+use std::fs;
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use libc::{c_int, gid_t, pid_t, uid_t};
+use crate::{ExecProgGuard, ExecProgInfo, SYS_EXEC_FAILED};
+
+/*
+ * User IDs of the pool are leased by locking a file per ID in this directory, so executions
+ * of all processes on the system exclude each other. Each execution gets a pair with equal
+ * user and group IDs, which nothing else is running as. Locks are released on close.
+ */
+const LEASE_LOCKS_PATH : &str = "/run/limtrac";
+
+// Shared directories, where programs may leave files behind them
+const SHARED_TEMP_PATHS : [&str; 3] = ["/tmp", "/var/tmp", "/dev/shm"];
+
+// Attempts to kill processes, which could be forked while others are killed
+const KILL_ATTEMPTS_MAX : c_int = 16;
+
+pub struct UidLease
+{
+    exec_uid   : uid_t,
+    exec_gid   : gid_t,           // Group with the same ID as the user
+    owner_pid  : pid_t,           // Child process must not release a lease of its parent
+    work_path  : PathBuf,
+    work_owner : (uid_t, gid_t),  // Owner of the working directory before it was leased
+    lease_lock : File             // Locked file of the user ID, unlocked after cleaning
+}
+
+impl UidLease
+{
+    /*
+     * Leases a free user ID of the pool (if it is enabled) and gives the working directory to it.
+     * Returns `errno` on failure, which is `EAGAIN` if all user IDs of the pool are leased.
+     */
+    pub fn acquire(exec_prog_info : &ExecProgInfo, exec_prog_guard : &ExecProgGuard) -> Result<Option<Self>, c_int>
+    {
+        if exec_prog_guard.uid_pool_size == 0 { return Ok(None); }
+
+        let pool_range = exec_prog_guard.uid_pool_first..exec_prog_guard.uid_pool_first + exec_prog_guard.uid_pool_size;
+        let mut leased_uid : Option<(uid_t, File)> = None;
+
+        for exec_uid in pool_range
+        {
+            if let Some(lease_lock) = lock_user_id(exec_uid)?
+            { leased_uid = Some((exec_uid, lease_lock)); break; }
+        }
+
+        let Some((exec_uid, lease_lock)) = leased_uid else { return Err(libc::EAGAIN) };
+        let exec_gid = exec_uid as gid_t;
+
+        let work_path = PathBuf::from(unsafe { CStr::from_ptr(exec_prog_info.working_path) }.to_str().unwrap());
+        let work_owner = fs::metadata(&work_path).map(|metadata| (metadata.uid(), metadata.gid())).map_err(get_errno)?;
+
+        std::os::unix::fs::chown(&work_path, Some(exec_uid), Some(exec_gid)).map_err(get_errno)?;
+
+        Ok(Some(Self {
+            exec_uid,
+            exec_gid,
+            owner_pid: unsafe { libc::getpid() },
+            work_path,
+            work_owner,
+            lease_lock
+        }))
+    }
+
+    pub fn get_ids(&self) -> (uid_t, gid_t)
+    {
+        (self.exec_uid, self.exec_gid)
+    }
+}
+
+impl Drop for UidLease
+{
+    fn drop(&mut self)
+    {
+        if unsafe { libc::getpid() } != self.owner_pid { return; }
+
+        // Nothing must run as the user, before its files are cleaned
+        kill_user_processes(self.exec_uid, self.exec_gid);
+
+        // Files in the working directory belong to the caller, others are removed
+        restore_owner(&self.work_path, (self.exec_uid, self.exec_gid), self.work_owner);
+        let _ = std::os::unix::fs::lchown(&self.work_path, Some(self.work_owner.0), Some(self.work_owner.1));
+        SHARED_TEMP_PATHS.iter().for_each(|temp_path| remove_user_files(Path::new(temp_path), self.exec_uid));
+
+        // Forked processes may still share the lock file, so it is unlocked explicitly
+        unsafe { libc::flock(self.lease_lock.as_raw_fd(), libc::LOCK_UN) };
+    }
+}
+
+/*
+ * Locks the file of a user ID, unless another execution (of any process) holds it. The
+ * directory of lock files must belong to the caller, so nobody else can plant or hold them.
+ */
+fn lock_user_id(exec_uid : uid_t) -> Result<Option<File>, c_int>
+{
+    let _ = DirBuilder::new().mode(0o700).create(LEASE_LOCKS_PATH);
+
+    let locks_metadata = fs::symlink_metadata(LEASE_LOCKS_PATH).map_err(get_errno)?;
+    if !locks_metadata.is_dir() || locks_metadata.uid() != unsafe { libc::geteuid() } { return Err(libc::EPERM); }
+
+    let lease_lock = OpenOptions::new().write(true).create(true).mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+        .open(Path::new(LEASE_LOCKS_PATH).join(format!("uid-{}.lock", exec_uid)))
+        .map_err(get_errno)?;
+
+    // Lock is held by the open file description, so it excludes executions of the same process too
+    if unsafe { libc::flock(lease_lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == SYS_EXEC_FAILED
+    {
+        let lock_errno = nix::errno::errno();
+        return if lock_errno == libc::EWOULDBLOCK { Ok(None) } else { Err(lock_errno) };
+    }
+
+    Ok(Some(lease_lock))
+}
+
+fn get_errno(io_error : std::io::Error) -> c_int
+{
+    io_error.raw_os_error().unwrap_or(libc::EIO)
+}
+
+/*
+ * Only a process of the user can signal all of its processes at once, using `kill(-1)`,
+ * so we fork a helper process, which changes its credentials and kills them. Processes
+ * may be forked while others are killed, so it repeats until none of them remained.
+ */
+fn kill_user_processes(exec_uid : uid_t, exec_gid : gid_t)
+{
+    let helper_pid = unsafe { libc::fork() };
+
+    if helper_pid == SYS_EXEC_FAILED
+    { crate::helper_functions::panic_on_syscall!("fork"); }
+
+    if helper_pid == 0
+    {
+        unsafe {
+            // Processes of the user must not be able to trace the helper process
+            libc::prctl(libc::PR_SET_DUMPABLE, 0);
+
+            if libc::setgroups(0, std::ptr::null()) == SYS_EXEC_FAILED
+                || libc::setresgid(exec_gid, exec_gid, exec_gid) == SYS_EXEC_FAILED
+                || libc::setresuid(exec_uid, exec_uid, exec_uid) == SYS_EXEC_FAILED
+            { libc::_exit(SYS_EXEC_FAILED); }
+
+            for _ in 0..KILL_ATTEMPTS_MAX
+            { if libc::kill(-1, libc::SIGKILL) == SYS_EXEC_FAILED { libc::_exit(0); } }
+
+            libc::_exit(SYS_EXEC_FAILED);
+        }
+    }
+
+    let mut helper_status : c_int = 0;
+    while unsafe { libc::waitpid(helper_pid, &mut helper_status, 0) } == SYS_EXEC_FAILED
+    { if nix::errno::errno() != libc::EINTR { crate::helper_functions::panic_on_syscall!("waitpid"); } }
+}
+
+/* Gives files of the user in a directory back to its owner, without following symbolic links */
+fn restore_owner(dir_path : &Path, exec_ids : (uid_t, gid_t), work_owner : (uid_t, gid_t))
+{
+    let Ok(dir_entries) = fs::read_dir(dir_path) else { return };
+
+    for dir_entry in dir_entries.flatten()
+    {
+        let Ok(metadata) = dir_entry.path().symlink_metadata() else { continue };
+
+        if metadata.uid() == exec_ids.0 || metadata.gid() == exec_ids.1
+        { let _ = std::os::unix::fs::lchown(dir_entry.path(), Some(work_owner.0), Some(work_owner.1)); }
+
+        if metadata.is_dir() { restore_owner(&dir_entry.path(), exec_ids, work_owner); }
+    }
+}
+
+/*
+ * Removes files and directories of the user, left at the top level of a shared directory.
+ * Files left in directories of other users are not searched for. Processes of the user are
+ * killed already, so its entries can't be replaced, and `remove_dir_all` doesn't follow
+ * symbolic links inside of them.
+ */
+fn remove_user_files(dir_path : &Path, exec_uid : uid_t)
+{
+    let Ok(dir_entries) = fs::read_dir(dir_path) else { return };
+
+    for dir_entry in dir_entries.flatten()
+    {
+        let Ok(metadata) = dir_entry.path().symlink_metadata() else { continue };
+        if metadata.uid() != exec_uid { continue; }
+
+        let _ = if metadata.is_dir() { fs::remove_dir_all(dir_entry.path()) } else { fs::remove_file(dir_entry.path()) };
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ * LIMTRAC, a part of Overtest free software project.
+ * Copyright (C) 2021-2023, Yurii Kadirov <contact@sirkadirov.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+/*
+ * Regression tests for SECCOMP denylist tiers. This binary runs itself under
+ * LIMTRAC for every denied system call, and the child process invokes the call
+ * (see `invoke_syscall`), so we can check it is killed or fails as expected.
+ */
+
+use std::ffi::{CStr, CString};
+use libc::{c_char, c_int, c_long};
+use limtrac::*;
+
+fn to_c_string(value: &str) -> *const c_char
+{ CString::new(value).unwrap().into_raw() }
+
+/*
+ * @Invoke a system call and report its result via the exit code. It runs before the test
+ * harness, which uses system calls denied by the tiers (for example, `prctl` to name threads).
+ */
+#[used]
+#[link_section = ".init_array"]
+static INVOKE_SYSCALL : extern "C" fn(c_int, *const *const c_char) = invoke_syscall;
+
+extern "C" fn invoke_syscall(argc: c_int, argv: *const *const c_char)
+{
+    let args : Vec<&str> = (0..argc as usize)
+        .map(|index| unsafe { CStr::from_ptr(*argv.add(index)) }.to_str().unwrap_or_default())
+        .collect();
+
+    // Only the child process, executed by `execute_invoke`, passes a system call to invoke
+    let (sys_call, arg0) : (c_long, u64) = match args.as_slice() {
+        [_, "--invoke", sys_call, arg0] => (sys_call.parse().unwrap(), arg0.parse().unwrap()),
+        _ => return
+    };
+
+    let result = unsafe { libc::syscall(sys_call, arg0, 0, 0, 0, 0, 0) };
+
+    // Child processes created by `clone` must not continue the test
+    if result == 0 && sys_call == libc::SYS_clone { unsafe { libc::_exit(0) }; }
+
+    let exit_code = if result == SYS_EXEC_FAILED as c_long { nix::errno::errno() } else { 0 };
+    unsafe { libc::_exit(exit_code) }
+}
+/* @/Invoke a system call and report its result via the exit code */
+
+fn execute_invoke(sys_call: c_long, arg0: u64, deny_tier: c_int) -> ProcExecResult
+{
+    let exec_path = std::env::current_exe().unwrap();
+    let work_path = std::env::temp_dir();
+
+    let exec_prog_info = ExecProgInfo {
+        program_path: to_c_string(exec_path.to_str().unwrap()),
+        program_args: to_c_string(&format!("--invoke {} {}", sys_call, arg0)),
+        working_path: to_c_string(work_path.to_str().unwrap()),
+        exec_as_user: to_c_string("")
+    };
+
+    let exec_prog_io = ExecProgIO {
+        io_redirected: false,
+        io_path_stdin: to_c_string(""),
+        io_path_stdout: to_c_string(""),
+        io_path_stderr: to_c_string(""),
+        io_dup_err_out: false,
+        io_unix_sockets: to_c_string(""),
+        io_keep_fds: to_c_string(""),
+        io_fd_stdin: -1,
+        io_fd_stdout: -1,
+        io_fd_stderr: -1,
+        io_fd_map: to_c_string(""),
+        io_mode_stdin: IO_MODE_AUTO,
+        io_mode_stdout: IO_MODE_AUTO,
+        io_mode_stderr: IO_MODE_AUTO,
+        io_open_policy: IO_OPEN_TRUNCATE,
+        io_file_mode: 0
+    };
+
+    let exec_prog_limits = ExecProgLimits {
+        limit_real_time: 5000,
+        limit_proc_time: 0,
+        limit_proc_wset: 0,
+        rlimit_enabled: false,
+        rlimit_core: 0,
+        rlimit_npoc: 0,
+        rlimit_nofile: 0
+    };
+
+    let exec_prog_guard = ExecProgGuard {
+        scmp_enabled: true,
+        scmp_deny_common: true,
+        scmp_deny_tier: deny_tier,
+        scmp_path_policy: false,
+        scmp_path_allow: to_c_string(""),
+        scmp_learn_mode: false,
+        scmp_learn_path: to_c_string(""),
+        scmp_profile_path: to_c_string(""),
+        scmp_deny_exec: false,
+        scmp_allow_compat: false,
+        unshare_common: false,
+        unshare_network: false,
+        unshare_namespaces: 0,
+        unshare_required: 0,
+        setns_join: to_c_string(""),
+        net_loopback_up: false,
+        mount_root_path: to_c_string(""),
+        mount_bind_ro: to_c_string(""),
+        mount_nosuid: false,
+        mount_nodev: false,
+        mount_noexec: false,
+        mount_tmpfs: false,
+        mount_tmpfs_paths: to_c_string(""),
+        mount_tmpfs_size: 0,
+        mount_tmpfs_inodes: 0,
+        mount_dev: false,
+        landlock_enabled: false,
+        landlock_read_paths: to_c_string(""),
+        priv_no_new_privs: false,
+        priv_drop_caps: false,
+        priv_not_dumpable: false,
+        uid_pool_first: 0,
+        uid_pool_size: 0,
+        signals_ignored: 0
+    };
+
+    limtrac_execute(exec_prog_info, exec_prog_io, exec_prog_limits, exec_prog_guard)
+}
+
+fn check_deny_rule(rule: &DenyRule)
+{
+    let arg0 = rule.arg0_masked.map(|(_, value)| value).unwrap_or(0);
+    let result = execute_invoke(rule.sys_call, arg0, rule.tier);
+
+    match rule.errno {
+        Some(errno) => assert!(!result.is_killed && result.exit_code == errno,
+            "System call {} must fail with error {} on tier {}", rule.sys_call, errno, rule.tier),
+        None => assert!(result.is_killed && result.kill_reason == KILL_REASON_SECURITY,
+            "System call {} (arg0 = {:#x}) must be killed on tier {}", rule.sys_call, arg0, rule.tier)
+    }
+}
+
+#[test]
+fn tiers_cumulative()
+{
+    let baseline = get_deny_rules(SCMP_DENY_TIER_BASELINE);
+    let hardened = get_deny_rules(SCMP_DENY_TIER_HARDENED);
+    let paranoid = get_deny_rules(SCMP_DENY_TIER_PARANOID);
+
+    assert!(baseline.len() < hardened.len() && hardened.len() < paranoid.len());
+    assert!(baseline.iter().all(|rule| hardened.contains(rule)));
+    assert!(hardened.iter().all(|rule| paranoid.contains(rule)));
+
+    // System calls that are commonly used to escape sandboxes must be denied by the hardened tier
+    let hardened_required = [
+        libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev, libc::SYS_mount,
+        libc::SYS_pivot_root, libc::SYS_bpf, libc::SYS_perf_event_open, libc::SYS_keyctl, libc::SYS_add_key,
+        libc::SYS_userfaultfd, libc::SYS_kexec_load, libc::SYS_init_module, libc::SYS_personality,
+        libc::SYS_setns, libc::SYS_clone3, libc::SYS_io_uring_setup
+    ];
+
+    for sys_call in hardened_required
+    { assert!(hardened.iter().any(|rule| rule.sys_call == sys_call), "System call {} is not denied", sys_call); }
+}
+
+#[test]
+fn allowed_syscalls()
+{
+    // Ordinary system calls are allowed even by the strictest tier
+    let result = execute_invoke(libc::SYS_getpid, 0, SCMP_DENY_TIER_PARANOID);
+    assert!(!result.is_killed && result.exit_code == 0, "System call 'getpid' must be allowed");
+
+    // Creating child processes without new namespaces is allowed by the hardened tier
+    let result = execute_invoke(libc::SYS_clone, libc::SIGCHLD as u64, SCMP_DENY_TIER_HARDENED);
+    assert!(!result.is_killed && result.exit_code == 0, "System call 'clone' must be allowed");
+}
+
+#[test]
+fn denied_syscalls()
+{
+    get_deny_rules(SCMP_DENY_TIER_PARANOID).iter().for_each(check_deny_rule);
+}